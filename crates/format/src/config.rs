@@ -1,14 +1,21 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::HashMap,
+    mem::Discriminant,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Mutex, MutexGuard},
+};
 
 use oxc_toml::Options as TomlFormatterOptions;
 use serde_json::Value;
 
 use oxc_formatter::{
-    FormatOptions,
+    FormatOptions, LineEnding, TrailingCommas,
     oxfmtrc::{OxfmtOptions, Oxfmtrc},
 };
 
 use super::FormatFileStrategy;
+use super::editorconfig::{EditorConfig, EditorConfigProperties};
 use super::support::JsonType;
 
 /// Resolve config file path from cwd and optional explicit path.
@@ -42,10 +49,48 @@ pub fn resolve_editorconfig_path(cwd: &Path) -> Option<PathBuf> {
         .find(|p| p.exists())
 }
 
+/// `endOfLine` option, where `auto` keeps the dominant line ending of each file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndOfLine {
+    Lf,
+    Crlf,
+    Cr,
+    Auto,
+}
+
+impl EndOfLine {
+    /// Fixed line ending, `None` for `auto`.
+    pub fn line_ending(self) -> Option<LineEnding> {
+        match self {
+            Self::Lf => Some(LineEnding::Lf),
+            Self::Crlf => Some(LineEnding::Crlf),
+            Self::Cr => Some(LineEnding::Cr),
+            Self::Auto => None,
+        }
+    }
+}
+
+impl FromStr for EndOfLine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lf" => Ok(Self::Lf),
+            "crlf" => Ok(Self::Crlf),
+            "cr" => Ok(Self::Cr),
+            "auto" => Ok(Self::Auto),
+            _ => Err(format!(
+                "Invalid line ending '{s}'. Values: lf, crlf, cr, auto"
+            )),
+        }
+    }
+}
+
 // ---
 
 /// Resolved options for each file type.
 /// Each variant contains only the options needed for that formatter.
+#[derive(Debug, Clone)]
 pub enum ResolvedOptions {
     /// For JS/TS files formatted by oxc_formatter.
     OxcFormatter {
@@ -80,33 +125,79 @@ pub enum ResolvedOptions {
     },
 }
 
+impl ResolvedOptions {
+    /// Replace the line ending, e.g. with the one detected for `endOfLine: "auto"`.
+    ///
+    /// NOTE: External formatters detect line endings themselves.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        match self {
+            Self::OxcFormatter { format_options, .. } => format_options.line_ending = line_ending,
+            Self::OxfmtToml { toml_options, .. } => {
+                toml_options.crlf = line_ending.is_carriage_return_line_feed();
+            }
+            Self::OxfmtJson { json_options, .. } => {
+                json_options.line_ending = json_line_ending(line_ending);
+            }
+            #[cfg(feature = "napi")]
+            Self::ExternalFormatter { .. } | Self::ExternalFormatterPackageJson { .. } => {}
+        }
+    }
+}
+
+/// Callback applied to the format options of every file after config files are resolved.
+type FormatOptionsOverrides = Box<dyn Fn(&mut FormatOptions, &FormatFileStrategy) + Send + Sync>;
+
+/// Files of the same kind with the same `.editorconfig` properties resolve to the same options.
+type ConfigScope = (
+    Discriminant<FormatFileStrategy>,
+    Option<JsonType>,
+    EditorConfigProperties,
+);
+
 /// Configuration resolver that derives all config values from a single `serde_json::Value`.
 pub struct ConfigResolver {
+    /// Directory that relative file paths are resolved against.
+    cwd: PathBuf,
     /// User's raw config as JSON value.
     raw_config: Value,
+    /// `.editorconfig` files next to each file, used for options not set in the raw config.
+    editorconfig: Option<EditorConfig>,
+    /// Overrides that take precedence over all config files.
+    format_options_overrides: Option<FormatOptionsOverrides>,
     /// Cached parsed options after validation.
     cached_options: Option<(FormatOptions, OxfmtOptions, Value)>,
+    /// Options resolved once per config scope.
+    resolved_scopes: Mutex<HashMap<ConfigScope, ResolvedOptions>>,
+    /// Keep the dominant line ending of each file, with `endOfLine: "auto"`.
+    auto_line_ending: bool,
 }
 
 impl ConfigResolver {
     /// Create a new resolver from a raw JSON config value.
     pub fn from_value(raw_config: Value) -> Self {
         Self {
+            cwd: PathBuf::new(),
             raw_config,
+            editorconfig: None,
+            format_options_overrides: None,
             cached_options: None,
+            resolved_scopes: Mutex::default(),
+            auto_line_ending: false,
         }
     }
 
     /// Create a resolver by loading config from a file path.
+    /// `.editorconfig` files are looked up in the directory of each resolved file and its ancestors,
+    /// up to the directory of `editorconfig_path` if it is given.
     ///
     /// # Errors
     /// Returns error if:
     /// - Config file is specified but not found or invalid
     /// - Config file parsing fails
     pub fn from_config_paths(
-        _cwd: &Path,
+        cwd: &Path,
         oxfmtrc_path: Option<&Path>,
-        _editorconfig_path: Option<&Path>,
+        editorconfig_path: Option<&Path>,
     ) -> Result<Self, String> {
         // Read and parse config file, or use empty JSON if not found
         let json_string = match oxfmtrc_path {
@@ -127,11 +218,24 @@ impl ConfigResolver {
             .map_err(|err| format!("Failed to parse config: {err}"))?;
 
         Ok(Self {
+            cwd: cwd.to_path_buf(),
             raw_config,
+            editorconfig: Some(match editorconfig_path.and_then(Path::parent) {
+                Some(dir) => EditorConfig::with_root(cwd.join(dir)),
+                None => EditorConfig::default(),
+            }),
+            format_options_overrides: None,
             cached_options: None,
+            resolved_scopes: Mutex::default(),
+            auto_line_ending: false,
         })
     }
 
+    /// Raw config value, e.g. to check whether an option was explicitly set.
+    pub fn raw_config(&self) -> &Value {
+        &self.raw_config
+    }
+
     /// Validate config and return ignore patterns for file walking.
     ///
     /// Validated options are cached for fast path resolution.
@@ -139,7 +243,16 @@ impl ConfigResolver {
     /// # Errors
     /// Returns error if config deserialization fails.
     pub fn build_and_validate(&mut self) -> Result<Vec<String>, String> {
-        let oxfmtrc: Oxfmtrc = serde_json::from_value(self.raw_config.clone())
+        let mut raw_config = self.raw_config.clone();
+        // `auto` is not a line ending by itself, it is resolved per file in `resolve_with_source()`
+        self.auto_line_ending = raw_config.get("endOfLine").and_then(Value::as_str) == Some("auto");
+        if self.auto_line_ending
+            && let Some(config) = raw_config.as_object_mut()
+        {
+            config.remove("endOfLine");
+        }
+
+        let oxfmtrc: Oxfmtrc = serde_json::from_value(raw_config)
             .map_err(|err| format!("Failed to deserialize Oxfmtrc: {err}"))?;
 
         // If not specified, default options are resolved here
@@ -155,17 +268,92 @@ impl ConfigResolver {
 
         // NOTE: Save cache for fast path
         self.cached_options = Some((format_options, oxfmt_options, external_options));
+        self.scopes().clear();
 
         Ok(ignore_patterns_clone)
     }
 
+    /// Override resolved format options, e.g. with CLI flags that take precedence over config files.
+    ///
+    /// The callback receives the strategy of the file, so overrides can depend on the file type.
+    ///
+    /// NOTE: External formatter options are not updated.
+    pub fn override_format_options(
+        &mut self,
+        apply: impl Fn(&mut FormatOptions, &FormatFileStrategy) + Send + Sync + 'static,
+    ) {
+        self.format_options_overrides = Some(Box::new(apply));
+        self.scopes().clear();
+    }
+
+    /// Override `endOfLine` of the config files, e.g. with `--line-ending`.
+    /// A fixed line ending is set with [`Self::override_format_options`].
+    pub fn set_auto_line_ending(&mut self, auto_line_ending: bool) {
+        self.auto_line_ending = auto_line_ending;
+    }
+
+    /// Resolve format options for a file along with its source,
+    /// which decides the line ending with `endOfLine: "auto"`.
+    pub fn resolve_with_source(
+        &self,
+        strategy: &FormatFileStrategy,
+        source_text: &str,
+    ) -> ResolvedOptions {
+        let mut resolved_options = self.resolve(strategy);
+        if self.auto_line_ending
+            && let Some(line_ending) = detect_line_ending(source_text)
+        {
+            resolved_options.set_line_ending(line_ending);
+        }
+        resolved_options
+    }
+
     /// Resolve format options for a specific file.
+    ///
+    /// Options are built once per config scope and cloned for the other files in it.
     pub fn resolve(&self, strategy: &FormatFileStrategy) -> ResolvedOptions {
-        let (format_options, oxfmt_options, external_options) = self
+        let properties = self
+            .editorconfig
+            .as_ref()
+            .map(|editorconfig| editorconfig.properties(&self.cwd.join(strategy.path())))
+            .unwrap_or_default();
+        let json_type = match strategy {
+            FormatFileStrategy::OxfmtJson { json_type, .. } => Some(*json_type),
+            _ => None,
+        };
+        let scope = (std::mem::discriminant(strategy), json_type, properties);
+
+        if let Some(resolved_options) = self.scopes().get(&scope) {
+            return resolved_options.clone();
+        }
+        let resolved_options = self.resolve_scope(strategy, &scope.2);
+        self.scopes().insert(scope, resolved_options.clone());
+        resolved_options
+    }
+
+    fn resolve_scope(
+        &self,
+        strategy: &FormatFileStrategy,
+        properties: &EditorConfigProperties,
+    ) -> ResolvedOptions {
+        let (mut format_options, mut oxfmt_options, external_options) = self
             .cached_options
             .clone()
             .expect("`build_and_validate()` must be called before `resolve()`");
 
+        self.apply_editorconfig(properties, &mut format_options, &mut oxfmt_options);
+        // JSON and TOML files have no trailing commas by default, unlike the `all` of JS files
+        let is_data_file = matches!(
+            strategy,
+            FormatFileStrategy::OxfmtJson { .. } | FormatFileStrategy::OxfmtToml { .. }
+        );
+        if is_data_file && self.raw_config.get("trailingComma").is_none() {
+            format_options.trailing_commas = TrailingCommas::None;
+        }
+        if let Some(apply) = &self.format_options_overrides {
+            apply(&mut format_options, strategy);
+        }
+
         let insert_final_newline = oxfmt_options.insert_final_newline;
 
         match strategy {
@@ -202,10 +390,79 @@ impl ConfigResolver {
             }
         }
     }
+
+    /// Apply `.editorconfig` properties for options that are not set in the raw config.
+    /// Invalid values are ignored, as the EditorConfig spec requires.
+    fn apply_editorconfig(
+        &self,
+        properties: &EditorConfigProperties,
+        format_options: &mut FormatOptions,
+        oxfmt_options: &mut OxfmtOptions,
+    ) {
+        let is_unset = |key: &str| self.raw_config.get(key).is_none();
+
+        if is_unset("useTabs")
+            && let Some(Ok(indent_style)) = properties.indent_style.as_deref().map(str::parse)
+        {
+            format_options.indent_style = indent_style;
+        }
+        if is_unset("tabWidth")
+            && let Some(Ok(indent_width)) = properties.indent_width().map(str::parse)
+        {
+            format_options.indent_width = indent_width;
+        }
+        if is_unset("endOfLine")
+            && let Some(Ok(line_ending)) = properties.end_of_line.as_deref().map(str::parse)
+        {
+            format_options.line_ending = line_ending;
+        }
+        if is_unset("printWidth")
+            && let Some(Ok(line_width)) = properties.max_line_length.as_deref().map(str::parse)
+        {
+            format_options.line_width = line_width;
+        }
+        if is_unset("insertFinalNewline")
+            && let Some(insert_final_newline) = properties.insert_final_newline
+        {
+            oxfmt_options.insert_final_newline = insert_final_newline;
+        }
+    }
+
+    fn scopes(&self) -> MutexGuard<'_, HashMap<ConfigScope, ResolvedOptions>> {
+        // The map stays consistent even if a formatting thread panicked
+        self.resolved_scopes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
 }
 
 // ---
 
+/// Most frequent line ending in the source, `None` if it has no line breaks.
+/// Ties are broken in favor of LF, then CRLF.
+fn detect_line_ending(source_text: &str) -> Option<LineEnding> {
+    let (mut lf, mut crlf, mut cr) = (0, 0, 0);
+    let mut bytes = source_text.bytes().peekable();
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'\r' if bytes.next_if_eq(&b'\n').is_some() => crlf += 1,
+            b'\r' => cr += 1,
+            b'\n' => lf += 1,
+            _ => {}
+        }
+    }
+
+    if lf + crlf + cr == 0 {
+        None
+    } else if lf >= crlf && lf >= cr {
+        Some(LineEnding::Lf)
+    } else if crlf >= cr {
+        Some(LineEnding::Crlf)
+    } else {
+        Some(LineEnding::Cr)
+    }
+}
+
 /// Build `toml` formatter options.
 /// The same as `prettier-plugin-toml`.
 fn build_toml_options(format_options: &FormatOptions) -> TomlFormatterOptions {
@@ -230,6 +487,7 @@ pub struct JsonFormatterOptions {
     pub indent_width: usize,
     pub use_tabs: bool,
     pub line_ending: String,
+    pub line_width: usize,
     pub trailing_commas: bool,
     pub quote_properties: json5format::QuoteProperties,
 }
@@ -239,12 +497,9 @@ fn build_json_options(format_options: &FormatOptions) -> JsonFormatterOptions {
     JsonFormatterOptions {
         indent_width: format_options.indent_width.value() as usize,
         use_tabs: format_options.indent_style.is_tab(),
-        line_ending: if format_options.line_ending.is_carriage_return_line_feed() {
-            "\r\n".to_string()
-        } else {
-            "\n".to_string()
-        },
-        trailing_commas: format_options.trailing_commas.is_none(),
+        line_ending: json_line_ending(format_options.line_ending),
+        line_width: format_options.line_width.value() as usize,
+        trailing_commas: !format_options.trailing_commas.is_none(),
         quote_properties: match format_options.quote_properties {
             oxc_formatter::QuoteProperties::AsNeeded => json5format::QuoteProperties::AsNeeded,
             oxc_formatter::QuoteProperties::Preserve => json5format::QuoteProperties::Preserve,
@@ -252,3 +507,88 @@ fn build_json_options(format_options: &FormatOptions) -> JsonFormatterOptions {
         },
     }
 }
+
+/// The JSON formatters only support LF and CRLF.
+fn json_line_ending(line_ending: LineEnding) -> String {
+    if line_ending.is_carriage_return_line_feed() {
+        "\r\n".to_string()
+    } else {
+        "\n".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use oxc_formatter::LineEnding;
+    use serde_json::json;
+
+    use super::{ConfigResolver, ResolvedOptions, detect_line_ending};
+    use crate::FormatFileStrategy;
+
+    #[test]
+    fn test_detect_line_ending() {
+        assert_eq!(detect_line_ending("a"), None);
+        assert_eq!(detect_line_ending("a\r\nb\r\nc\n"), Some(LineEnding::Crlf));
+        assert_eq!(detect_line_ending("a\rb\r"), Some(LineEnding::Cr));
+        assert_eq!(detect_line_ending("a\r\nb\n"), Some(LineEnding::Lf));
+    }
+
+    #[test]
+    fn test_auto_line_ending() {
+        let mut config_resolver = ConfigResolver::from_value(json!({ "endOfLine": "auto" }));
+        config_resolver
+            .build_and_validate()
+            .expect("`auto` should be accepted");
+        let strategy = FormatFileStrategy::try_from(PathBuf::from("module.json5"))
+            .expect("JSON5 should be supported");
+
+        let line_ending =
+            |source_text| match config_resolver.resolve_with_source(&strategy, source_text) {
+                ResolvedOptions::OxfmtJson { json_options, .. } => json_options.line_ending,
+                _ => unreachable!("JSON5 resolves to JSON options"),
+            };
+        assert_eq!(line_ending("{\r\n}\r\n"), "\r\n");
+        assert_eq!(line_ending("{\n}\n"), "\n");
+        // Files without line breaks use the default
+        assert_eq!(line_ending("{}"), "\n");
+    }
+
+    #[test]
+    fn test_trailing_commas() {
+        let json5 = FormatFileStrategy::try_from(PathBuf::from("module.json5"))
+            .expect("JSON5 should be supported");
+        let toml = FormatFileStrategy::try_from(PathBuf::from("Cargo.toml"))
+            .expect("TOML should be supported");
+
+        let cases = [
+            (json!({}), false),
+            (json!({ "trailingComma": "none" }), false),
+            (json!({ "trailingComma": "es5" }), true),
+            (json!({ "trailingComma": "all" }), true),
+        ];
+        for (config, expected) in cases {
+            let trailing_commas = config.to_string();
+            let mut config_resolver = ConfigResolver::from_value(config);
+            config_resolver
+                .build_and_validate()
+                .expect("Config should be valid");
+            match config_resolver.resolve(&json5) {
+                ResolvedOptions::OxfmtJson { json_options, .. } => {
+                    assert_eq!(json_options.trailing_commas, expected, "{trailing_commas}");
+                }
+                _ => unreachable!("JSON5 resolves to JSON options"),
+            }
+            match config_resolver.resolve(&toml) {
+                ResolvedOptions::OxfmtToml { toml_options, .. } => {
+                    assert_eq!(
+                        toml_options.array_trailing_comma, expected,
+                        "{trailing_commas}"
+                    );
+                }
+                _ => unreachable!("TOML resolves to TOML options"),
+            }
+        }
+    }
+}
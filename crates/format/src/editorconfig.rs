@@ -0,0 +1,303 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use globset::{GlobBuilder, GlobMatcher};
+
+/// `.editorconfig` files that apply to formatted files, found in the directory of each file
+/// and its ancestors until `root = true` or the root directory of [`Self::with_root`].
+///
+/// Files are read once per directory, so nested files can override the ones above them.
+///
+/// See <https://editorconfig.org/#file-format-details>.
+/// Numeric ranges like `{1..3}` in section globs are not supported.
+#[derive(Debug, Default)]
+pub struct EditorConfig {
+    /// Files that apply in each visited directory, ordered from the farthest to the nearest one.
+    files: Mutex<HashMap<PathBuf, Arc<Vec<Arc<EditorConfigFile>>>>>,
+    /// Directory above which no files are read.
+    root: Option<PathBuf>,
+}
+
+/// A single parsed `.editorconfig` file.
+#[derive(Debug)]
+struct EditorConfigFile {
+    sections: Vec<Section>,
+    is_root: bool,
+}
+
+#[derive(Debug)]
+struct Section {
+    matcher: GlobMatcher,
+    properties: Vec<(String, String)>,
+}
+
+/// Properties that apply to a single file.
+/// Later sections and nearer files take precedence over earlier ones.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct EditorConfigProperties {
+    pub indent_style: Option<String>,
+    pub indent_size: Option<String>,
+    pub tab_width: Option<String>,
+    pub end_of_line: Option<String>,
+    pub max_line_length: Option<String>,
+    pub insert_final_newline: Option<bool>,
+}
+
+impl EditorConfig {
+    /// Read no files above `root`, as if its `.editorconfig` had `root = true`.
+    pub fn with_root(root: PathBuf) -> Self {
+        Self {
+            files: Mutex::default(),
+            root: Some(root),
+        }
+    }
+
+    /// Collect the properties that apply to the file at the absolute `path`.
+    pub fn properties(&self, path: &Path) -> EditorConfigProperties {
+        let mut properties = EditorConfigProperties::default();
+        if let Some(dir) = path.parent() {
+            for file in self.files(dir).iter() {
+                file.apply(path, &mut properties);
+            }
+        }
+        properties
+    }
+
+    /// Files that apply in `dir`, reading each directory at most once.
+    fn files(&self, dir: &Path) -> Arc<Vec<Arc<EditorConfigFile>>> {
+        if let Some(files) = self.cache().get(dir) {
+            return files.clone();
+        }
+
+        // Unreadable files are skipped, as if they did not exist
+        let file = super::utils::read_to_string(&dir.join(".editorconfig"))
+            .ok()
+            .map(|source| Arc::new(EditorConfigFile::parse(&source, dir)));
+        let is_root =
+            file.as_ref().is_some_and(|file| file.is_root) || self.root.as_deref() == Some(dir);
+        let mut files = match dir.parent() {
+            Some(parent) if !is_root => self.files(parent).as_ref().clone(),
+            _ => Vec::new(),
+        };
+        files.extend(file);
+
+        let files = Arc::new(files);
+        self.cache().insert(dir.to_path_buf(), files.clone());
+        files
+    }
+
+    fn cache(&self) -> MutexGuard<'_, HashMap<PathBuf, Arc<Vec<Arc<EditorConfigFile>>>>> {
+        self.files.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl EditorConfigFile {
+    /// Parse the file in `dir`, which its section globs are relative to.
+    fn parse(source: &str, dir: &Path) -> Self {
+        let (sections, is_root) = parse(source, dir);
+        Self { sections, is_root }
+    }
+
+    /// Apply the sections matching `path`, later sections take precedence.
+    fn apply(&self, path: &Path, properties: &mut EditorConfigProperties) {
+        for section in self.sections.iter().filter(|s| s.matcher.is_match(path)) {
+            for (key, value) in &section.properties {
+                let value = Some(value.clone());
+                match key.as_str() {
+                    "indent_style" => properties.indent_style = value,
+                    "indent_size" => properties.indent_size = value,
+                    "tab_width" => properties.tab_width = value,
+                    "end_of_line" => properties.end_of_line = value,
+                    "max_line_length" => properties.max_line_length = value,
+                    "insert_final_newline" => {
+                        properties.insert_final_newline = match value.as_deref() {
+                            Some("true") => Some(true),
+                            Some("false") => Some(false),
+                            _ => None,
+                        };
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+impl EditorConfigProperties {
+    /// Indent width, falling back to `tab_width` when `indent_size = tab`.
+    pub fn indent_width(&self) -> Option<&str> {
+        match self.indent_size.as_deref() {
+            Some("tab") => self.tab_width.as_deref(),
+            Some(size) => Some(size),
+            None if self.indent_style.as_deref() == Some("tab") => self.tab_width.as_deref(),
+            None => None,
+        }
+    }
+}
+
+/// Parse sections of a single file and whether it is marked with `root = true`.
+fn parse(source: &str, dir: &Path) -> (Vec<Section>, bool) {
+    let mut sections = Vec::new();
+    let mut is_root = false;
+    let mut current: Option<Section> = None;
+
+    for line in source.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(glob) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.extend(current.take());
+            // Sections with invalid globs are skipped along with their properties
+            current = build_matcher(glob, dir).map(|matcher| Section {
+                matcher,
+                properties: Vec::new(),
+            });
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        // Values of known properties are case insensitive
+        let value = value.trim().to_ascii_lowercase();
+
+        match &mut current {
+            Some(section) => section.properties.push((key, value)),
+            // Properties before the first section are only allowed for `root`
+            None if key == "root" => is_root = value == "true",
+            None => {}
+        }
+    }
+    sections.extend(current);
+
+    (sections, is_root)
+}
+
+/// Build a matcher for a section glob, relative to the directory of the `.editorconfig` file.
+fn build_matcher(glob: &str, dir: &Path) -> Option<GlobMatcher> {
+    // Globs without `/` match files in any subdirectory
+    let relative = match glob.strip_prefix('/') {
+        Some(glob) => glob.to_string(),
+        None if glob.contains('/') => glob.to_string(),
+        None => format!("**/{glob}"),
+    };
+    let dir = globset::escape(&dir.to_string_lossy());
+    let pattern = if dir.is_empty() {
+        relative
+    } else {
+        format!("{}/{relative}", dir.trim_end_matches('/'))
+    };
+
+    GlobBuilder::new(&pattern)
+        .literal_separator(true)
+        .build()
+        .ok()
+        .map(|glob| glob.compile_matcher())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct ParsedFile(EditorConfigFile);
+
+    impl ParsedFile {
+        fn properties(&self, path: &Path) -> EditorConfigProperties {
+            let mut properties = EditorConfigProperties::default();
+            self.0.apply(path, &mut properties);
+            properties
+        }
+    }
+
+    fn parse_editorconfig(source: &str) -> ParsedFile {
+        ParsedFile(EditorConfigFile::parse(source, Path::new("/project")))
+    }
+
+    const EDITORCONFIG: &str = r"
+root = true
+
+# Defaults
+[*]
+indent_style = space
+indent_size = 2
+end_of_line = lf
+insert_final_newline = true
+
+[*.ets]
+indent_size = 4
+max_line_length = 120
+
+[*.{json5,json}]
+indent_style = Tab
+insert_final_newline = false
+
+[/entry/src/main/ets/legacy/**]
+end_of_line = crlf
+";
+
+    #[test]
+    fn test_editorconfig_glob_sections() {
+        let config = parse_editorconfig(EDITORCONFIG);
+
+        let ets = config.properties(Path::new("/project/entry/src/main/ets/pages/Index.ets"));
+        assert_eq!(ets.indent_style.as_deref(), Some("space"));
+        assert_eq!(ets.indent_width(), Some("4"));
+        assert_eq!(ets.end_of_line.as_deref(), Some("lf"));
+        assert_eq!(ets.max_line_length.as_deref(), Some("120"));
+        assert_eq!(ets.insert_final_newline, Some(true));
+
+        let json5 = config.properties(Path::new("/project/entry/src/main/module.json5"));
+        assert_eq!(json5.indent_style.as_deref(), Some("tab"));
+        assert_eq!(json5.insert_final_newline, Some(false));
+        assert_eq!(json5.max_line_length, None);
+    }
+
+    #[test]
+    fn test_editorconfig_path_sections() {
+        let config = parse_editorconfig(EDITORCONFIG);
+
+        let legacy = config.properties(Path::new("/project/entry/src/main/ets/legacy/Old.ets"));
+        assert_eq!(legacy.end_of_line.as_deref(), Some("crlf"));
+
+        let outside = config.properties(Path::new("/other/entry/src/main/ets/legacy/Old.ets"));
+        assert_eq!(outside, EditorConfigProperties::default());
+    }
+
+    #[test]
+    fn test_editorconfig_with_root() {
+        let dir = std::env::temp_dir().join(format!("format-editorconfig-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("project/src")).expect("Failed to create temp dir");
+        std::fs::write(dir.join(".editorconfig"), "[*]\nindent_size = 8\n")
+            .expect("Failed to write .editorconfig");
+        std::fs::write(
+            dir.join("project/.editorconfig"),
+            "[*]\nmax_line_length = 120\n",
+        )
+        .expect("Failed to write .editorconfig");
+
+        let path = dir.join("project/src/a.ts");
+        let properties = EditorConfig::default().properties(&path);
+        assert_eq!(properties.indent_width(), Some("8"));
+
+        // Files above the root are not read
+        let properties = EditorConfig::with_root(dir.join("project")).properties(&path);
+        assert_eq!(properties.indent_width(), None);
+        assert_eq!(properties.max_line_length.as_deref(), Some("120"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_editorconfig_indent_size_tab() {
+        let config =
+            parse_editorconfig("[*]\nindent_style = tab\nindent_size = tab\ntab_width = 8\n");
+        let properties = config.properties(Path::new("/project/a.ts"));
+        assert_eq!(properties.indent_width(), Some("8"));
+    }
+}
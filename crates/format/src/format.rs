@@ -10,6 +10,7 @@ use oxc_span::SourceType;
 use serde_json::Value;
 
 use super::config::JsonFormatterOptions;
+use super::json::Document;
 use super::support::JsonType;
 use super::{FormatFileStrategy, ResolvedOptions};
 
@@ -224,119 +225,37 @@ impl SourceFormatter {
 // --- JSON formatting functions
 
 /// Format standard JSON file.
+/// Key order, number literals and escape sequences are kept, only whitespace changes.
 fn format_json(source_text: &str, options: &JsonFormatterOptions) -> Result<String, OxcDiagnostic> {
-    // Parse JSON
-    let value: serde_json::Value = serde_json::from_str(source_text)
-        .map_err(|err| OxcDiagnostic::error(format!("Failed to parse JSON: {err}")))?;
-
-    // Format with serde_json
-    let formatted = serde_json::to_string_pretty(&value)
-        .map_err(|err| OxcDiagnostic::error(format!("Failed to format JSON: {err}")))?;
-
-    // Replace indentation and line endings
-    let formatted = if options.use_tabs {
-        // Replace spaces with tabs
-        replace_indent(&formatted, options.indent_width, "\t")
-    } else {
-        formatted
-    };
-
-    let formatted = formatted.replace('\n', &options.line_ending);
-
-    Ok(formatted)
+    print_json(source_text, JsonType::Json, options)
 }
 
 /// Format JSON5 file (supports comments, trailing commas, etc.).
-/// Uses json5format to preserve comments and format JSON5 files.
+/// Comments and literals are preserved, keys are quoted according to `quote_properties`.
 fn format_json5(
     source_text: &str,
     options: &JsonFormatterOptions,
 ) -> Result<String, OxcDiagnostic> {
-    use json5format::{FormatOptions, Json5Format, ParsedDocument};
-
-    // Parse the JSON5 document (preserves comments)
-    let parsed = ParsedDocument::from_str(source_text, None)
-        .map_err(|err| OxcDiagnostic::error(format!("Failed to parse JSON5: {err}")))?;
-
-    // Create format options
-    let mut format_options = FormatOptions::default();
-    // Note: json5format uses indent_by as usize (number of spaces), tabs are not directly supported
-    // We'll use spaces and then replace with tabs if needed
-    let indent_by = if options.use_tabs {
-        1 // Will be replaced with tabs later
-    } else {
-        options.indent_width
-    };
-    format_options.indent_by = indent_by;
-    format_options.trailing_commas = options.trailing_commas;
-    format_options.quote_properties = options.quote_properties;
-
-    // Create formatter with options
-    let formatter = Json5Format::with_options(format_options)
-        .map_err(|err| OxcDiagnostic::error(format!("Failed to create JSON5 formatter: {err}")))?;
-
-    // Format the JSON5 (preserves comments)
-    let mut formatted = formatter
-        .to_string(&parsed)
-        .map_err(|err| OxcDiagnostic::error(format!("Failed to format JSON5: {err}")))?;
-
-    // Replace spaces with tabs if needed
-    if options.use_tabs {
-        formatted = replace_indent(&formatted, indent_by, "\t");
-    }
-
-    // Replace line endings
-    formatted = formatted.replace('\n', &options.line_ending);
-
-    Ok(formatted)
+    print_json(source_text, JsonType::Json5, options)
 }
 
 /// Format JSONC file (JSON with comments).
+/// Comments are preserved, everything else is printed as strict JSON.
 fn format_jsonc(
     source_text: &str,
     options: &JsonFormatterOptions,
 ) -> Result<String, OxcDiagnostic> {
-    // First, strip comments to get valid JSON
-    let mut json_text = source_text.to_string();
-    json_strip_comments::strip(&mut json_text).map_err(|err| {
-        OxcDiagnostic::error(format!("Failed to strip comments from JSONC: {err}"))
-    })?;
-
-    // Then format as standard JSON
-    format_json(&json_text, options)
+    print_json(source_text, JsonType::Jsonc, options)
 }
 
-/// Replace indentation in formatted JSON string.
-fn replace_indent(text: &str, original_width: usize, new_indent: &str) -> String {
-    let lines: Vec<&str> = text.lines().collect();
-    let mut result = String::new();
-
-    for (i, line) in lines.iter().enumerate() {
-        if i > 0 {
-            result.push('\n');
-        }
-
-        // Count leading spaces (using bytes for safe indexing)
-        let leading_spaces_byte_count = line.bytes().take_while(|&b| b == b' ').count();
-
-        if leading_spaces_byte_count > 0
-            && leading_spaces_byte_count <= line.len()
-            && original_width > 0
-        {
-            // Calculate number of indent levels
-            let indent_levels = leading_spaces_byte_count / original_width;
-            // Replace with new indent
-            for _ in 0..indent_levels {
-                result.push_str(new_indent);
-            }
-            // Add remaining content (safe because we checked bounds)
-            result.push_str(&line[leading_spaces_byte_count..]);
-        } else {
-            result.push_str(line);
-        }
-    }
-
-    result
+/// Format JSON, JSONC or JSON5 through the lossless syntax tree of `json`.
+fn print_json(
+    source_text: &str,
+    json_type: JsonType,
+    options: &JsonFormatterOptions,
+) -> Result<String, OxcDiagnostic> {
+    let document = Document::parse(source_text, json_type)?;
+    Ok(document.print(options).replace('\n', &options.line_ending))
 }
 
 impl SourceFormatter {
@@ -432,6 +351,7 @@ mod tests {
             indent_width: 2,
             use_tabs: false,
             line_ending: "\n".to_string(),
+            line_width: 80,
             trailing_commas: true,
             quote_properties: json5format::QuoteProperties::Consistent,
         };
@@ -460,6 +380,7 @@ mod tests {
             indent_width: 2,
             use_tabs: false,
             line_ending: "\n".to_string(),
+            line_width: 80,
             trailing_commas: false,
             quote_properties: json5format::QuoteProperties::Consistent,
         };
@@ -493,6 +414,7 @@ mod tests {
             indent_width: 2,
             use_tabs: false,
             line_ending: "\n".to_string(),
+            line_width: 80,
             trailing_commas: true,
             quote_properties: json5format::QuoteProperties::Consistent,
         };
@@ -508,13 +430,18 @@ mod tests {
     fn test_format_json5_with_tabs() {
         let source = r#"{
   name: 'test',
-  version: '1.0.0'
+  /* Block comment
+   * keeps its spaces
+   */
+  version: ['1.0.0', 'line \
+  continuation']
 }"#;
 
         let options = JsonFormatterOptions {
             indent_width: 2,
             use_tabs: true,
             line_ending: "\n".to_string(),
+            line_width: 80,
             trailing_commas: false,
             quote_properties: json5format::QuoteProperties::Consistent,
         };
@@ -522,9 +449,11 @@ mod tests {
         let result = format_json5(source, &options);
         assert!(result.is_ok(), "JSON5 with tabs should format successfully");
         let formatted = result.unwrap();
-        // Note: json5format uses spaces for indentation, tabs are applied via post-processing
-        // in the format_json5 function
-        assert!(!formatted.is_empty(), "Formatted JSON5 should not be empty");
+        // Only structural indentation becomes tabs, comments and strings are kept as written
+        assert_eq!(
+            formatted,
+            "{\n\tname: 'test',\n\t/* Block comment\n   * keeps its spaces\n   */\n\tversion: [\n\t\t'1.0.0',\n\t\t'line \\\n  continuation'\n\t]\n}\n"
+        );
     }
 
     #[test]
@@ -538,6 +467,7 @@ mod tests {
             indent_width: 2,
             use_tabs: false,
             line_ending: "\r\n".to_string(),
+            line_width: 80,
             trailing_commas: false,
             quote_properties: json5format::QuoteProperties::Consistent,
         };
@@ -564,6 +494,7 @@ mod tests {
             indent_width: 2,
             use_tabs: false,
             line_ending: "\n".to_string(),
+            line_width: 80,
             trailing_commas: false,
             quote_properties: json5format::QuoteProperties::Consistent,
         };
@@ -580,6 +511,7 @@ mod tests {
             indent_width: 2,
             use_tabs: false,
             line_ending: "\n".to_string(),
+            line_width: 80,
             trailing_commas: false,
             quote_properties: json5format::QuoteProperties::Consistent,
         };
@@ -592,6 +524,35 @@ mod tests {
         assert!(formatted.contains("test"), "Should contain 'test'");
     }
 
+    #[test]
+    fn test_format_json_is_lossless() {
+        let source = r#"{"version":"1.0.0","name":"test","price":1.50,"size":1e3,"id":12345678901234567890,"text":"\u4e2d\/"}"#;
+
+        let options = JsonFormatterOptions {
+            indent_width: 2,
+            use_tabs: false,
+            line_ending: "\n".to_string(),
+            line_width: 80,
+            trailing_commas: false,
+            quote_properties: json5format::QuoteProperties::Consistent,
+        };
+
+        // Keys are not sorted and literals are not rewritten
+        let formatted = format_json(source, &options).expect("JSON formatting should succeed");
+        assert_eq!(
+            formatted,
+            r#"{
+  "version": "1.0.0",
+  "name": "test",
+  "price": 1.50,
+  "size": 1e3,
+  "id": 12345678901234567890,
+  "text": "\u4e2d\/"
+}
+"#
+        );
+    }
+
     #[test]
     fn test_format_jsonc_basic() {
         let source = r#"{
@@ -606,6 +567,7 @@ mod tests {
             indent_width: 2,
             use_tabs: false,
             line_ending: "\n".to_string(),
+            line_width: 80,
             trailing_commas: false,
             quote_properties: json5format::QuoteProperties::Consistent,
         };
@@ -613,16 +575,8 @@ mod tests {
         let result = format_jsonc(source, &options);
         assert!(result.is_ok(), "JSONC formatting should succeed");
         let formatted = result.unwrap();
-        assert!(!formatted.is_empty(), "Formatted JSONC should not be empty");
-        // Comments should be stripped, so formatted JSON should not contain comment markers
-        assert!(
-            !formatted.contains("//"),
-            "Comments should be stripped from JSONC"
-        );
-        assert!(
-            !formatted.contains("/*"),
-            "Comments should be stripped from JSONC"
-        );
+        // Comments are kept, the rest is already formatted
+        assert_eq!(formatted, format!("{source}\n"));
     }
 
     #[test]
@@ -638,6 +592,7 @@ mod tests {
             indent_width: 2,
             use_tabs: false,
             line_ending: "\n".to_string(),
+            line_width: 80,
             trailing_commas: false,
             quote_properties: json5format::QuoteProperties::Consistent,
         };
@@ -694,6 +649,7 @@ mod tests {
             indent_width: 2,
             use_tabs: false,
             line_ending: "\n".to_string(),
+            line_width: 80,
             trailing_commas: false,
             quote_properties: json5format::QuoteProperties::Consistent,
         };
@@ -720,6 +676,7 @@ mod tests {
             indent_width: 2,
             use_tabs: false,
             line_ending: "\n".to_string(),
+            line_width: 80,
             trailing_commas: false,
             quote_properties: json5format::QuoteProperties::Consistent,
         };
@@ -757,6 +714,7 @@ mod tests {
             indent_width: 2,
             use_tabs: false,
             line_ending: "\n".to_string(),
+            line_width: 80,
             trailing_commas: false,
             quote_properties: json5format::QuoteProperties::Consistent,
         };
@@ -799,6 +757,7 @@ mod tests {
             indent_width: 2,
             use_tabs: false,
             line_ending: "\n".to_string(),
+            line_width: 80,
             trailing_commas: false,
             quote_properties: json5format::QuoteProperties::Consistent,
         };
@@ -825,6 +784,7 @@ mod tests {
             indent_width: 2,
             use_tabs: false,
             line_ending: "\n".to_string(),
+            line_width: 80,
             trailing_commas: false,
             quote_properties: json5format::QuoteProperties::Preserve,
         };
@@ -0,0 +1,918 @@
+//! Comment-preserving syntax tree and printer for JSON, JSONC and JSON5 files.
+//!
+//! Literals are kept as written, so only whitespace, layout and (for JSON5) quotes of keys change.
+
+use std::borrow::Cow;
+
+use json5format::QuoteProperties;
+use oxc_diagnostics::OxcDiagnostic;
+use oxc_span::Span;
+
+use crate::config::JsonFormatterOptions;
+use crate::support::JsonType;
+
+/// A comment, kept verbatim.
+#[derive(Debug)]
+struct Comment {
+    text: String,
+    line_breaks_before: usize,
+    /// At least one for line comments.
+    line_breaks_after: usize,
+}
+
+impl Comment {
+    fn line_break_before(&self) -> bool {
+        self.line_breaks_before > 0
+    }
+
+    fn line_break_after(&self) -> bool {
+        self.line_breaks_after > 0
+    }
+
+    fn blank_line_after(&self) -> bool {
+        self.line_breaks_after > 1
+    }
+}
+
+#[derive(Debug)]
+enum Value {
+    Container(Container),
+    /// Strings, numbers and keywords as written in the source.
+    Literal(String),
+}
+
+/// An object or an array.
+#[derive(Debug)]
+struct Container {
+    is_object: bool,
+    items: Vec<Item>,
+    /// Comments after the last item.
+    dangling_comments: Vec<Comment>,
+    /// Whether the source has a line break right after the opening bracket.
+    line_break_after_open: bool,
+}
+
+/// A property of an object or an element of an array.
+#[derive(Debug)]
+struct Item {
+    /// Comments before the item, including those between the key and the value.
+    leading_comments: Vec<Comment>,
+    /// The key as written, `None` for array elements.
+    key: Option<String>,
+    value: Value,
+    /// Comments on the same line after the value.
+    trailing_comments: Vec<Comment>,
+    /// Whether the source has a blank line between the previous item and this one.
+    blank_line_before: bool,
+}
+
+impl Item {
+    fn is_object(&self) -> bool {
+        matches!(&self.value, Value::Container(container) if container.is_object)
+    }
+}
+
+#[derive(Debug)]
+pub struct Document {
+    json_type: JsonType,
+    leading_comments: Vec<Comment>,
+    value: Value,
+    trailing_comments: Vec<Comment>,
+}
+
+impl Document {
+    /// Parse `source_text` with the syntax of `json_type`.
+    pub fn parse(source_text: &str, json_type: JsonType) -> Result<Self, OxcDiagnostic> {
+        let mut parser = Parser {
+            source_text,
+            pos: 0,
+            json_type,
+            line_breaks: 0,
+        };
+        let leading_comments = parser.comments()?;
+        let value = parser.value()?;
+        let trailing_comments = parser.comments()?;
+        if parser.pos < source_text.len() {
+            return Err(parser.error("Unexpected content after the value"));
+        }
+        Ok(Self {
+            json_type,
+            leading_comments,
+            value,
+            trailing_comments,
+        })
+    }
+
+    /// Print the document, ending with a newline.
+    ///
+    /// Objects and arrays are printed on one line when they fit within `line_width`,
+    /// except objects with a line break after `{` in the source, as Prettier does.
+    /// JSON and JSONC are printed without trailing commas, keeping them strict.
+    pub fn print(&self, options: &JsonFormatterOptions) -> String {
+        let is_json5 = self.json_type == JsonType::Json5;
+        let mut printer = Printer {
+            out: String::new(),
+            indent: if options.use_tabs {
+                "\t".to_string()
+            } else {
+                " ".repeat(options.indent_width)
+            },
+            tab_width: options.indent_width,
+            line_width: options.line_width,
+            trailing_commas: is_json5 && options.trailing_commas,
+            quote_properties: is_json5.then_some(options.quote_properties),
+        };
+        for comment in &self.leading_comments {
+            printer.out.push_str(&comment.text);
+            if comment.blank_line_after() {
+                printer.out.push('\n');
+            }
+            printer.out.push(if comment.line_break_after() {
+                '\n'
+            } else {
+                ' '
+            });
+        }
+        printer.value(&self.value, 0, 0);
+        for comment in &self.trailing_comments {
+            if comment.line_breaks_before > 1 {
+                printer.out.push('\n');
+            }
+            printer.out.push(if comment.line_break_before() {
+                '\n'
+            } else {
+                ' '
+            });
+            printer.out.push_str(&comment.text);
+        }
+        printer.out.push('\n');
+        printer.out
+    }
+}
+
+struct Parser<'a> {
+    source_text: &'a str,
+    pos: usize,
+    json_type: JsonType,
+    /// Line breaks right before the current token.
+    line_breaks: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.source_text.as_bytes().get(self.pos + offset).copied()
+    }
+
+    fn is_json5(&self) -> bool {
+        self.json_type == JsonType::Json5
+    }
+
+    fn error(&self, message: &str) -> OxcDiagnostic {
+        let start = self.pos.min(self.source_text.len());
+        let end = self.source_text[start..]
+            .chars()
+            .next()
+            .map_or(start, |c| start + c.len_utf8());
+        let name = match self.json_type {
+            JsonType::Json => "JSON",
+            JsonType::Jsonc => "JSONC",
+            JsonType::Json5 => "JSON5",
+        };
+        OxcDiagnostic::error(format!("Failed to parse {name}: {message}"))
+            .with_label(Span::new(start as u32, end as u32))
+    }
+
+    /// Skip whitespace and collect the comments up to the next token.
+    fn comments(&mut self) -> Result<Vec<Comment>, OxcDiagnostic> {
+        let mut comments: Vec<Comment> = Vec::new();
+        let mut line_breaks = 0;
+        loop {
+            match self.peek() {
+                Some(b'\n') => {
+                    line_breaks += 1;
+                    self.pos += 1;
+                }
+                Some(b' ' | b'\t' | b'\r') => self.pos += 1,
+                Some(b'/') => {
+                    if self.json_type == JsonType::Json {
+                        return Err(self.error("Comments are not allowed in JSON"));
+                    }
+                    let rest = &self.source_text[self.pos..];
+                    let (text, is_line) = if rest.starts_with("//") {
+                        let end = rest.find('\n').unwrap_or(rest.len());
+                        (rest[..end].trim_end_matches('\r'), true)
+                    } else if let Some(body) = rest.strip_prefix("/*") {
+                        let end = body
+                            .find("*/")
+                            .ok_or_else(|| self.error("Unterminated comment"))?;
+                        (&rest[..end + 4], false)
+                    } else {
+                        return Err(self.error("Unexpected character"));
+                    };
+                    self.pos += text.len();
+                    if let Some(previous) = comments.last_mut() {
+                        previous.line_breaks_after = line_breaks;
+                    }
+                    comments.push(Comment {
+                        text: text.replace("\r\n", "\n"),
+                        line_breaks_before: line_breaks,
+                        line_breaks_after: usize::from(is_line),
+                    });
+                    line_breaks = 0;
+                }
+                _ => break,
+            }
+        }
+        if let Some(last) = comments.last_mut() {
+            last.line_breaks_after = last.line_breaks_after.max(line_breaks);
+        }
+        self.line_breaks = line_breaks;
+        Ok(comments)
+    }
+
+    fn value(&mut self) -> Result<Value, OxcDiagnostic> {
+        match self.peek() {
+            Some(b'{') => Ok(Value::Container(self.container(true)?)),
+            Some(b'[') => Ok(Value::Container(self.container(false)?)),
+            Some(b'"') => self.string().map(Value::Literal),
+            Some(b'\'') if self.is_json5() => self.string().map(Value::Literal),
+            Some(b'-' | b'0'..=b'9') => self.number().map(Value::Literal),
+            Some(b'+' | b'.' | b'I' | b'N') if self.is_json5() => self.number().map(Value::Literal),
+            Some(b'a'..=b'z') => {
+                let start = self.pos;
+                while self.peek().is_some_and(|b| b.is_ascii_alphanumeric()) {
+                    self.pos += 1;
+                }
+                match &self.source_text[start..self.pos] {
+                    keyword @ ("true" | "false" | "null") => {
+                        Ok(Value::Literal(keyword.to_string()))
+                    }
+                    _ => {
+                        self.pos = start;
+                        Err(self.error("Unexpected keyword"))
+                    }
+                }
+            }
+            Some(_) => Err(self.error("Unexpected character")),
+            None => Err(self.error("Unexpected end of input")),
+        }
+    }
+
+    /// Parse an object or an array, starting at its opening bracket.
+    fn container(&mut self, is_object: bool) -> Result<Container, OxcDiagnostic> {
+        self.pos += 1;
+        let close = if is_object { b'}' } else { b']' };
+        let line_break_after_open = self.source_text[self.pos..]
+            .bytes()
+            .take_while(|b| matches!(b, b' ' | b'\t' | b'\r' | b'\n'))
+            .any(|b| b == b'\n');
+
+        let mut items = Vec::new();
+        let mut pending_comments = self.comments()?;
+        loop {
+            if self.peek() == Some(close) {
+                self.pos += 1;
+                return Ok(Container {
+                    is_object,
+                    items,
+                    dangling_comments: pending_comments,
+                    line_break_after_open,
+                });
+            }
+
+            // Blank lines are kept between items, but not after the opening bracket
+            let blank_line_before = !items.is_empty()
+                && pending_comments
+                    .first()
+                    .map_or(self.line_breaks, |comment| comment.line_breaks_before)
+                    > 1;
+            let mut leading_comments = std::mem::take(&mut pending_comments);
+            let key = if is_object {
+                let key = self.key()?;
+                leading_comments.extend(self.comments()?);
+                if self.peek() != Some(b':') {
+                    return Err(self.error("Expected ':'"));
+                }
+                self.pos += 1;
+                leading_comments.extend(self.comments()?);
+                Some(key)
+            } else {
+                None
+            };
+            let value = self.value()?;
+
+            // Comments on the line of the value stay after it, the others belong to the next item
+            let mut trailing_comments = Vec::new();
+            let mut comments = self.comments()?;
+            if self.peek() == Some(b',') {
+                self.pos += 1;
+                comments.extend(self.comments()?);
+                if self.peek() == Some(close) && self.json_type == JsonType::Json {
+                    return Err(self.error("Trailing commas are not allowed in JSON"));
+                }
+            } else if self.peek() != Some(close) {
+                return Err(self.error(if is_object {
+                    "Expected ',' or '}'"
+                } else {
+                    "Expected ',' or ']'"
+                }));
+            }
+            let same_line = comments
+                .iter()
+                .take_while(|comment| !comment.line_break_before())
+                .count();
+            pending_comments = comments.split_off(same_line);
+            trailing_comments.extend(comments);
+
+            items.push(Item {
+                leading_comments,
+                key,
+                value,
+                trailing_comments,
+                blank_line_before,
+            });
+        }
+    }
+
+    /// Parse a property name, JSON5 also allows single quotes and identifiers.
+    fn key(&mut self) -> Result<String, OxcDiagnostic> {
+        match self.peek() {
+            Some(b'"') => self.string(),
+            Some(b'\'') if self.is_json5() => self.string(),
+            Some(b'0'..=b'9') | None => Err(self.error("Expected a property name")),
+            Some(_) if self.is_json5() => self.identifier(),
+            Some(_) => Err(self.error("Expected a property name in double quotes")),
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, OxcDiagnostic> {
+        let start = self.pos;
+        while let Some(b) = self.peek() {
+            match b {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'$' => self.pos += 1,
+                b'\\' => {
+                    if !self.is_hex_escape(1, b'u', 4) {
+                        return Err(self.error("Invalid unicode escape"));
+                    }
+                    self.pos += 6;
+                }
+                0x80.. => match self.source_text[self.pos..].chars().next() {
+                    Some(c) if c.is_alphanumeric() => self.pos += c.len_utf8(),
+                    _ => break,
+                },
+                _ => break,
+            }
+        }
+        if self.pos == start {
+            return Err(self.error("Expected a property name"));
+        }
+        Ok(self.source_text[start..self.pos].to_string())
+    }
+
+    /// Whether `offset` bytes ahead is `prefix` followed by `digits` hex digits.
+    fn is_hex_escape(&self, offset: usize, prefix: u8, digits: usize) -> bool {
+        let start = self.pos + offset;
+        self.peek_at(offset) == Some(prefix)
+            && self
+                .source_text
+                .get(start + 1..start + 1 + digits)
+                .is_some_and(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+    }
+
+    /// Parse a quoted string, returning it as written.
+    fn string(&mut self) -> Result<String, OxcDiagnostic> {
+        let start = self.pos;
+        let quote = self.source_text.as_bytes()[start];
+        self.pos += 1;
+        loop {
+            match self.peek() {
+                Some(b) if b == quote => break,
+                Some(b'\\') => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(b'u') if self.is_hex_escape(0, b'u', 4) => self.pos += 4,
+                        Some(b'x') if self.is_json5() && self.is_hex_escape(0, b'x', 2) => {
+                            self.pos += 2;
+                        }
+                        Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => {}
+                        // JSON5 line continuation
+                        Some(b'\r') if self.is_json5() && self.peek_at(1) == Some(b'\n') => {
+                            self.pos += 1;
+                        }
+                        Some(b'u' | b'x' | b'1'..=b'9') | None => {
+                            return Err(self.error("Invalid escape sequence"));
+                        }
+                        Some(_) if self.is_json5() => {}
+                        Some(_) => return Err(self.error("Invalid escape sequence")),
+                    }
+                }
+                Some(b'\n' | b'\r') | None => return Err(self.error("Unterminated string")),
+                Some(b) if b < 0x20 && !self.is_json5() => {
+                    return Err(self.error("Control character in string"));
+                }
+                Some(_) => {}
+            }
+            self.pos += 1;
+        }
+        self.pos += 1;
+        Ok(self.source_text[start..self.pos].replace("\r\n", "\n"))
+    }
+
+    /// Parse a number, returning it as written.
+    fn number(&mut self) -> Result<String, OxcDiagnostic> {
+        let start = self.pos;
+        let digits = |parser: &mut Self| {
+            let digits_start = parser.pos;
+            while parser.peek().is_some_and(|b| b.is_ascii_digit()) {
+                parser.pos += 1;
+            }
+            parser.pos > digits_start
+        };
+
+        if self.peek() == Some(b'-') || (self.is_json5() && self.peek() == Some(b'+')) {
+            self.pos += 1;
+        }
+        if self.is_json5() {
+            let rest = &self.source_text[self.pos..];
+            if let Some(keyword) = ["Infinity", "NaN"]
+                .into_iter()
+                .find(|keyword| rest.starts_with(keyword))
+            {
+                self.pos += keyword.len();
+                return Ok(self.source_text[start..self.pos].to_string());
+            }
+            if rest.starts_with("0x") || rest.starts_with("0X") {
+                self.pos += 2;
+                let hex_start = self.pos;
+                while self.peek().is_some_and(|b| b.is_ascii_hexdigit()) {
+                    self.pos += 1;
+                }
+                if self.pos == hex_start {
+                    return Err(self.error("Invalid number"));
+                }
+                return Ok(self.source_text[start..self.pos].to_string());
+            }
+        }
+
+        let integer_start = self.pos;
+        let has_integer = digits(self);
+        // JSON5 allows `.5` and `5.`
+        let json5_fraction = self.is_json5() && self.peek() == Some(b'.');
+        if !has_integer && !json5_fraction {
+            return Err(self.error("Invalid number"));
+        }
+        if has_integer
+            && self.source_text.as_bytes()[integer_start] == b'0'
+            && self.pos - integer_start > 1
+        {
+            self.pos = integer_start;
+            return Err(self.error("Leading zeros are not allowed"));
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            let json5_integer = self.is_json5() && has_integer;
+            if !digits(self) && !json5_integer {
+                return Err(self.error("Invalid number"));
+            }
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            self.pos += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if !digits(self) {
+                return Err(self.error("Invalid number"));
+            }
+        }
+        Ok(self.source_text[start..self.pos].to_string())
+    }
+}
+
+/// Whether `container` has to be printed on multiple lines.
+fn must_break(container: &Container) -> bool {
+    let has_comments = !container.dangling_comments.is_empty()
+        || container
+            .items
+            .iter()
+            .any(|item| !item.leading_comments.is_empty() || !item.trailing_comments.is_empty());
+    // Like Prettier, arrays of several objects (or arrays) with several items are expanded
+    let is_matrix = !container.is_object
+        && container.items.len() > 1
+        && container.items.iter().all(|item| {
+            matches!(&item.value, Value::Container(child)
+                if child.items.len() > 1 && child.is_object == container.items[0].is_object())
+        });
+    has_comments
+        || is_matrix
+        || container.items.iter().any(|item| item.blank_line_before)
+        || (container.is_object && container.line_break_after_open && !container.items.is_empty())
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+fn key_text(key: &str, quote_keys: Option<bool>) -> Cow<'_, str> {
+    let is_quoted = key.starts_with(['"', '\'']);
+    match quote_keys {
+        Some(true) if !is_quoted => Cow::Owned(format!("\"{key}\"")),
+        Some(true) if key.starts_with('\'') => Cow::Owned(double_quoted(key)),
+        Some(false) if is_quoted && is_identifier(&key[1..key.len() - 1]) => {
+            Cow::Borrowed(&key[1..key.len() - 1])
+        }
+        _ => Cow::Borrowed(key),
+    }
+}
+
+/// Rewrite a single-quoted JSON5 string in double quotes, keeping its other escapes.
+fn double_quoted(single_quoted: &str) -> String {
+    let mut text = String::with_capacity(single_quoted.len() + 2);
+    text.push('"');
+    let mut chars = single_quoted[1..single_quoted.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('\'') => text.push('\''),
+                Some(escaped) => {
+                    text.push('\\');
+                    text.push(escaped);
+                }
+                None => text.push('\\'),
+            },
+            '"' => text.push_str("\\\""),
+            c => text.push(c),
+        }
+    }
+    text.push('"');
+    text
+}
+
+/// A container printed on one line, given up once it exceeds the width left on the line.
+struct Flat {
+    text: String,
+    width: usize,
+    limit: usize,
+}
+
+impl Flat {
+    fn push(&mut self, text: &str) -> bool {
+        self.width += text.chars().count();
+        self.text.push_str(text);
+        self.width <= self.limit
+    }
+}
+
+struct Printer {
+    out: String,
+    /// A tab or `indent_width` spaces.
+    indent: String,
+    /// Width of a tab when measuring lines.
+    tab_width: usize,
+    line_width: usize,
+    trailing_commas: bool,
+    /// `None` keeps keys as written, as JSON and JSONC require.
+    quote_properties: Option<QuoteProperties>,
+}
+
+impl Printer {
+    fn new_line(&mut self, depth: usize) {
+        self.out.push('\n');
+        for _ in 0..depth {
+            self.out.push_str(&self.indent);
+        }
+    }
+
+    fn column(&self) -> usize {
+        let line_start = self.out.rfind('\n').map_or(0, |index| index + 1);
+        self.out[line_start..]
+            .chars()
+            .map(|c| if c == '\t' { self.tab_width } else { 1 })
+            .sum()
+    }
+
+    /// Print `value`, on one line if it fits along with the `suffix_width` characters after it.
+    fn value(&mut self, value: &Value, depth: usize, suffix_width: usize) {
+        let container = match value {
+            Value::Literal(literal) => {
+                self.out.push_str(literal);
+                return;
+            }
+            Value::Container(container) => container,
+        };
+
+        let mut flat = Flat {
+            text: String::new(),
+            width: 0,
+            limit: self.line_width.saturating_sub(self.column() + suffix_width),
+        };
+        if self.flat(value, &mut flat) {
+            self.out.push_str(&flat.text);
+        } else {
+            self.expanded(container, depth);
+        }
+    }
+
+    /// Print `value` on one line into `flat`, returning false if it cannot be or does not fit.
+    fn flat(&self, value: &Value, flat: &mut Flat) -> bool {
+        let container = match value {
+            // JSON5 strings can continue on the next line
+            Value::Literal(literal) => return !literal.contains('\n') && flat.push(literal),
+            Value::Container(container) => container,
+        };
+        if container.items.is_empty() && container.dangling_comments.is_empty() {
+            return flat.push(if container.is_object { "{}" } else { "[]" });
+        }
+        if must_break(container) || !flat.push(if container.is_object { "{ " } else { "[" }) {
+            return false;
+        }
+
+        let quote_keys = self.quote_keys(container);
+        for (index, item) in container.items.iter().enumerate() {
+            if index > 0 && !flat.push(", ") {
+                return false;
+            }
+            if let Some(key) = &item.key
+                && !(flat.push(&key_text(key, quote_keys)) && flat.push(": "))
+            {
+                return false;
+            }
+            if !self.flat(&item.value, flat) {
+                return false;
+            }
+        }
+        flat.push(if container.is_object { " }" } else { "]" })
+    }
+
+    fn expanded(&mut self, container: &Container, depth: usize) {
+        let (open, close) = if container.is_object {
+            ('{', '}')
+        } else {
+            ('[', ']')
+        };
+        self.out.push(open);
+        if container.items.is_empty() && container.dangling_comments.is_empty() {
+            self.out.push(close);
+            return;
+        }
+
+        let quote_keys = self.quote_keys(container);
+        for (index, item) in container.items.iter().enumerate() {
+            // At most one blank line is kept
+            if item.blank_line_before {
+                self.out.push('\n');
+            }
+            self.new_line(depth + 1);
+            for comment in &item.leading_comments {
+                self.out.push_str(&comment.text);
+                if comment.line_break_after() {
+                    if comment.blank_line_after() {
+                        self.out.push('\n');
+                    }
+                    self.new_line(depth + 1);
+                } else {
+                    self.out.push(' ');
+                }
+            }
+            if let Some(key) = &item.key {
+                self.out.push_str(&key_text(key, quote_keys));
+                self.out.push_str(": ");
+            }
+            let comma = index + 1 < container.items.len() || self.trailing_commas;
+            self.value(&item.value, depth + 1, usize::from(comma));
+            if comma {
+                self.out.push(',');
+            }
+            for comment in &item.trailing_comments {
+                self.out.push(' ');
+                self.out.push_str(&comment.text);
+            }
+        }
+        for (index, comment) in container.dangling_comments.iter().enumerate() {
+            if comment.line_breaks_before > 1 && (index > 0 || !container.items.is_empty()) {
+                self.out.push('\n');
+            }
+            self.new_line(depth + 1);
+            self.out.push_str(&comment.text);
+        }
+        self.new_line(depth);
+        self.out.push(close);
+    }
+
+    /// Whether the keys of `container` are all quoted (`Some(true)`), unquoted where possible
+    /// (`Some(false)`) or kept as written (`None`).
+    fn quote_keys(&self, container: &Container) -> Option<bool> {
+        match self.quote_properties? {
+            QuoteProperties::Always => Some(true),
+            QuoteProperties::AsNeeded => Some(false),
+            QuoteProperties::Preserve => None,
+            // Quote all keys if any of them is quoted or has to be
+            QuoteProperties::Consistent => container
+                .items
+                .iter()
+                .filter_map(|item| item.key.as_deref())
+                .any(|key| key.starts_with(['"', '\'']) || !is_identifier(key))
+                .then_some(true),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Document;
+    use crate::config::JsonFormatterOptions;
+    use crate::support::JsonType;
+
+    fn format(source_text: &str, json_type: JsonType) -> String {
+        let options = JsonFormatterOptions {
+            indent_width: 2,
+            use_tabs: false,
+            line_ending: "\n".to_string(),
+            line_width: 40,
+            trailing_commas: false,
+            quote_properties: json5format::QuoteProperties::Preserve,
+        };
+        Document::parse(source_text, json_type)
+            .expect("Source should parse")
+            .print(&options)
+    }
+
+    #[test]
+    fn test_comments_are_kept() {
+        let source = r#"// Compiler options
+{
+  // Target
+  "target": "es2020", // Inline
+  /* Block */ "strict": true,
+  "paths": {
+    /* No paths yet */
+  },
+  "lib": ["es2020",
+    // DOM is not available
+  ],
+}
+"#;
+        assert_eq!(
+            format(source, JsonType::Jsonc),
+            r#"// Compiler options
+{
+  // Target
+  "target": "es2020", // Inline
+  /* Block */ "strict": true,
+  "paths": {
+    /* No paths yet */
+  },
+  "lib": [
+    "es2020"
+    // DOM is not available
+  ]
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_literals_are_kept() {
+        let source = r#"{
+"price":1.50,"big":12345678901234567890,"text":"é\n"}"#;
+        assert_eq!(
+            format(source, JsonType::Jsonc),
+            r#"{
+  "price": 1.50,
+  "big": 12345678901234567890,
+  "text": "é\n"
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_line_width() {
+        let source = r#"{
+  "short": [1, 2, 3],
+  "object": {"a": 1, "b": 2},
+  "expanded": {
+    "a": 1},
+  "long": ["ohos.permission.INTERNET", "ohos.permission.GET_NETWORK_INFO"],
+  "matrix": [{"a": 1, "b": 2}, {"a": 3, "b": 4}]
+}"#;
+        assert_eq!(
+            format(source, JsonType::Json),
+            r#"{
+  "short": [1, 2, 3],
+  "object": { "a": 1, "b": 2 },
+  "expanded": {
+    "a": 1
+  },
+  "long": [
+    "ohos.permission.INTERNET",
+    "ohos.permission.GET_NETWORK_INFO"
+  ],
+  "matrix": [
+    { "a": 1, "b": 2 },
+    { "a": 3, "b": 4 }
+  ]
+}
+"#
+        );
+        // Without a line break after `{`, a short object is collapsed
+        assert_eq!(
+            format(r#"{"a": [], "b": {}}"#, JsonType::Json),
+            "{ \"a\": [], \"b\": {} }\n"
+        );
+    }
+
+    #[test]
+    fn test_blank_lines() {
+        let source = r#"// Header
+
+{
+
+  "a": 1,
+
+
+  "b": 2,
+  // Group
+
+  "c": [1,
+
+    2]
+}
+"#;
+        assert_eq!(
+            format(source, JsonType::Jsonc),
+            r#"// Header
+
+{
+  "a": 1,
+
+  "b": 2,
+  // Group
+
+  "c": [
+    1,
+
+    2
+  ]
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_json5_syntax() {
+        let source = "{unquoted: 'single', $id: +Infinity, hex: 0xFF, half: .5, text: 'a\\\nb', trailing: [1,],}";
+        assert_eq!(
+            format(source, JsonType::Json5),
+            "{\n  unquoted: 'single',\n  $id: +Infinity,\n  hex: 0xFF,\n  half: .5,\n  text: 'a\\\nb',\n  trailing: [1]\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_json5_keys_are_double_quoted() {
+        let options = JsonFormatterOptions {
+            indent_width: 2,
+            use_tabs: false,
+            line_ending: "\n".to_string(),
+            line_width: 80,
+            trailing_commas: false,
+            quote_properties: json5format::QuoteProperties::Always,
+        };
+        let source = r#"{a: 1, 'b': 2, 'it\'s': 3, 'say "hi"': 4, 'tab\t': 5}"#;
+        assert_eq!(
+            Document::parse(source, JsonType::Json5)
+                .expect("Source should parse")
+                .print(&options),
+            r#"{ "a": 1, "b": 2, "it's": 3, "say \"hi\"": 4, "tab\t": 5 }
+"#
+        );
+    }
+
+    #[test]
+    fn test_strict_syntax() {
+        for source in [
+            "{name: 1}",
+            "{'name': 1}",
+            "[0x10]",
+            "[01]",
+            "[.5]",
+            "[1,,]",
+            "[1] 2",
+            "[/* unterminated ]",
+        ] {
+            assert!(
+                Document::parse(source, JsonType::Jsonc).is_err(),
+                "{source} should not parse"
+            );
+        }
+        assert!(Document::parse("[1,]", JsonType::Json).is_err());
+        assert!(Document::parse("// comment\n[]", JsonType::Json).is_err());
+    }
+}
@@ -1,5 +1,7 @@
 mod config;
+mod editorconfig;
 mod format;
+mod json;
 mod support;
 mod utils;
 
@@ -7,11 +9,12 @@ mod utils;
 mod external_formatter;
 
 pub use config::{
-    ConfigResolver, JsonFormatterOptions, ResolvedOptions, resolve_editorconfig_path,
+    ConfigResolver, EndOfLine, JsonFormatterOptions, ResolvedOptions, resolve_editorconfig_path,
     resolve_oxfmtrc_path,
 };
 pub use format::{FormatResult, SourceFormatter};
 pub use support::{FormatFileStrategy, JsonType, should_ignore_file};
+pub use utils::{decode_utf8, read_to_string};
 
 #[cfg(feature = "napi")]
 pub use external_formatter::{
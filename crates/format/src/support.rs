@@ -27,7 +27,7 @@ pub enum FormatFileStrategy {
 }
 
 /// JSON file type for formatting
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum JsonType {
     /// Standard JSON
     Json,
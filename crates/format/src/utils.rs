@@ -1,8 +1,12 @@
 use std::{fs, io, path::Path};
 
 pub fn read_to_string(path: &Path) -> io::Result<String> {
+    decode_utf8(fs::read(path)?)
+}
+
+/// Decode bytes as strict UTF-8, e.g. file content that was read asynchronously.
+pub fn decode_utf8(bytes: Vec<u8>) -> io::Result<String> {
     // `simdutf8` is faster than `std::str::from_utf8` which `fs::read_to_string` uses internally
-    let bytes = fs::read(path)?;
     if simdutf8::basic::from_utf8(&bytes).is_err() {
         // Same error as `fs::read_to_string` produces (using `io::ErrorKind::InvalidData`)
         return Err(io::Error::new(
@@ -1,5 +1,5 @@
-use bpaf::{Parser, construct, long, positional};
-use std::str::FromStr;
+use bpaf::{Parser, construct, long, positional, short};
+use std::{path::PathBuf, str::FromStr};
 
 pub fn cli_format() -> impl Parser<crate::Options> {
     let file = positional("input")
@@ -18,6 +18,102 @@ pub fn cli_format() -> impl Parser<crate::Options> {
         .many()
         .fallback(vec![]);
 
+    let check = long("check")
+        .help("Check if files are formatted without writing them.")
+        .switch();
+
+    let diff = long("diff")
+        .help("Print a unified diff of the changes without writing files.")
+        .switch();
+
+    let stdin_filepath = long("stdin-filepath")
+        .argument::<PathBuf>("PATH")
+        .help("Format code from stdin to stdout, using PATH to detect the file type.")
+        .optional();
+
+    let config = long("config")
+        .short('c')
+        .argument::<PathBuf>("PATH")
+        .help("Path to the config file. Defaults to the nearest .oxfmtrc.json or .oxfmtrc.jsonc.")
+        .optional();
+
+    let watch = long("watch")
+        .short('w')
+        .help("Watch the matched files and format them when they change.")
+        .switch();
+
+    let cache = long("cache")
+        .help("Skip files that are unchanged since they were last formatted.")
+        .switch();
+
+    let cache_location = long("cache-location")
+        .argument::<PathBuf>("PATH")
+        .help("Path to the cache file, enables --cache. Defaults to .oxk-cache.")
+        .optional();
+
+    let reporter = long("reporter")
+        .argument::<String>("NAME")
+        .help(
+            "Output format of the results. Values: default, json, sarif, checkstyle, junit, github",
+        )
+        .parse(|s| crate::format::Reporter::from_str(&s))
+        .fallback(crate::format::Reporter::Default);
+
+    let staged = long("staged")
+        .help("Format staged files in the git index and stage the result.")
+        .switch();
+
+    let changed = long("changed")
+        .help("Only format files with uncommitted changes in git.")
+        .switch();
+
+    let since = long("since")
+        .argument::<String>("REV")
+        .help("Only format files changed in git since REV.")
+        .optional();
+
+    let always_color = long("color")
+        .help("Always use colors in the output.")
+        .req_flag(true);
+    let never_color = long("no-color")
+        .help("Never use colors in the output.")
+        .req_flag(false);
+    let color = construct!([always_color, never_color]).optional();
+
+    let fail_fast = long("fail-fast")
+        .help("Stop at the first file that fails to format instead of formatting the others.")
+        .switch();
+
+    let encoding = long("encoding")
+        .argument::<String>("NAME")
+        .help("Decode and write files in a legacy encoding such as gbk or latin1. Defaults to strict UTF-8.")
+        .parse(|s| match encoding_rs::Encoding::for_label(s.as_bytes()) {
+            // UTF-16 can only be decoded, encoding_rs writes it as UTF-8
+            Some(encoding) if encoding.output_encoding() == encoding => Ok(encoding),
+            Some(encoding) => Err(format!("Files cannot be written in {}", encoding.name())),
+            None => Err(format!("Unknown encoding '{s}'")),
+        })
+        .optional();
+
+    let out_dir = long("out-dir")
+        .argument::<PathBuf>("DIR")
+        .help("Write formatted copies to DIR, mirroring paths relative to the current directory, instead of formatting in place.")
+        .optional();
+
+    let files_from = long("files-from")
+        .argument::<PathBuf>("PATH")
+        .help("Format the files listed in PATH, or stdin for -, one per line. Paths are not globs.")
+        .optional();
+
+    let null_separated = short('0')
+        .long("null")
+        .help("Paths in --files-from are separated by NUL, e.g. from `git ls-files -z`.")
+        .switch();
+
+    let follow_symlinks = long("follow-symlinks")
+        .help("Traverse symlinked directories. Loops and files outside of the current directory are skipped.")
+        .switch();
+
     // FormatOptions parameters
     let indent_style = long("indent-style")
         .argument::<String>("STYLE")
@@ -33,8 +129,8 @@ pub fn cli_format() -> impl Parser<crate::Options> {
 
     let line_ending = long("line-ending")
         .argument::<String>("ENDING")
-        .help("The type of line ending. Values: lf, crlf, cr")
-        .parse(|s| oxc_formatter::LineEnding::from_str(&s))
+        .help("The type of line ending. Values: lf, crlf, cr, auto (keep the most common one of each file)")
+        .parse(|s| format::EndOfLine::from_str(&s))
         .optional();
 
     let line_width = long("line-width")
@@ -123,6 +219,24 @@ pub fn cli_format() -> impl Parser<crate::Options> {
     let format_parser = construct!(crate::FormatArgs {
         thread,
         excludes,
+        check,
+        diff,
+        stdin_filepath,
+        config,
+        watch,
+        cache,
+        cache_location,
+        reporter,
+        staged,
+        changed,
+        since,
+        color,
+        fail_fast,
+        encoding,
+        out_dir,
+        files_from,
+        null_separated,
+        follow_symlinks,
         indent_style,
         indent_width,
         line_ending,
@@ -0,0 +1,144 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+use serde_json::{Map, Value, json};
+use xxhash_rust::xxh3::Xxh3;
+
+/// Default cache file, relative to the current directory.
+pub const DEFAULT_CACHE_LOCATION: &str = ".oxk-cache";
+
+/// The cache is discarded when it was written by another version of oxk.
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// On-disk cache of files known to be formatted.
+///
+/// Each file path maps to the hash of its content, resolved options and the oxk version,
+/// so any change to one of them makes the entry stale.
+pub struct FormatCache {
+    location: PathBuf,
+    entries: Mutex<HashMap<String, u64>>,
+}
+
+impl FormatCache {
+    /// Load the cache from `location`, starting empty if it is missing, outdated or corrupt.
+    pub fn load(location: &Path) -> Self {
+        let location = if location.is_dir() {
+            location.join(DEFAULT_CACHE_LOCATION)
+        } else {
+            location.to_path_buf()
+        };
+
+        let entries = match std::fs::read_to_string(&location) {
+            Ok(source) => parse_entries(&source).unwrap_or_else(|| {
+                eprintln!(
+                    "Warning: Ignoring corrupt cache file '{}'",
+                    location.display()
+                );
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+
+        Self {
+            location,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    /// Hash file content together with a fingerprint of the options it is formatted with.
+    pub fn key(source: &[u8], options_fingerprint: &str) -> u64 {
+        let mut hasher = Xxh3::new();
+        hasher.update(VERSION.as_bytes());
+        hasher.update(&[0]);
+        hasher.update(options_fingerprint.as_bytes());
+        hasher.update(&[0]);
+        hasher.update(source);
+        hasher.digest()
+    }
+
+    /// Whether the file at `path` was formatted with the same content and options.
+    pub fn is_formatted(&self, path: &Path, key: u64) -> bool {
+        self.lock().get(path.to_string_lossy().as_ref()) == Some(&key)
+    }
+
+    /// Record that the file at `path` is formatted.
+    pub fn insert(&self, path: &Path, key: u64) {
+        self.lock().insert(path.to_string_lossy().into_owned(), key);
+    }
+
+    /// Write the cache to disk, replacing the previous file atomically.
+    pub fn save(&self) -> Result<(), String> {
+        let files: Map<String, Value> = self
+            .lock()
+            .iter()
+            .map(|(path, key)| (path.clone(), json!(key)))
+            .collect();
+        let content = json!({ "version": VERSION, "files": files }).to_string();
+
+        super::write::write_atomic(&self.location, content.as_bytes()).map_err(|e| {
+            format!(
+                "Failed to write cache file '{}': {}",
+                self.location.display(),
+                e
+            )
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, u64>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Parse cache entries, returning `None` if the file is corrupt.
+/// A cache written by another oxk version is valid but empty.
+fn parse_entries(source: &str) -> Option<HashMap<String, u64>> {
+    let value: Value = serde_json::from_str(source).ok()?;
+    if value.get("version")?.as_str()? != VERSION {
+        return Some(HashMap::new());
+    }
+    value
+        .get("files")?
+        .as_object()?
+        .iter()
+        .map(|(path, key)| Some((path.clone(), key.as_u64()?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::FormatCache;
+    use crate::format::test_utils::TempDir;
+
+    #[test]
+    fn test_cache_round_trip() {
+        let dir = TempDir::new("cache");
+        let path = Path::new("/project/entry/src/main/ets/pages/Index.ets");
+        let key = FormatCache::key(b"let a = 1\n", "options");
+
+        let cache = FormatCache::load(dir.path());
+        assert!(!cache.is_formatted(path, key));
+        cache.insert(path, key);
+        cache.save().expect("Cache should be saved");
+
+        let cache = FormatCache::load(dir.path());
+        assert!(cache.is_formatted(path, key));
+        assert!(!cache.is_formatted(path, FormatCache::key(b"let a = 1\n", "other")));
+
+        // A corrupt cache is ignored and overwritten on the next save
+        std::fs::write(dir.path().join(".oxk-cache"), "{ not json")
+            .expect("Failed to write temp file");
+        let cache = FormatCache::load(dir.path());
+        assert!(!cache.is_formatted(path, key));
+        cache.save().expect("Cache should be saved");
+        assert!(
+            FormatCache::load(&dir.path().join(".oxk-cache"))
+                .lock()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use std::fmt::Write;
+
+use owo_colors::OwoColorize;
+use similar::{ChangeTag, TextDiff};
+
+/// Number of unchanged lines shown around each change.
+const CONTEXT_RADIUS: usize = 3;
+
+/// Shown in place of a carriage return on changed lines of colored output.
+const CR_MARKER: &str = "␍";
+
+/// Render a unified diff between the original and the formatted text of a file.
+///
+/// Lines keep their exact line endings, so the diff can be applied as a patch and changes
+/// that only affect line endings are visible. Colored output marks carriage returns on changed
+/// lines instead, since a terminal does not show them.
+///
+/// Returns an empty string when both texts are identical.
+pub fn unified_diff(path: &str, original: &str, formatted: &str, colored: bool) -> String {
+    let diff = TextDiff::from_lines(original, formatted);
+    let mut output = String::new();
+
+    for (index, hunk) in diff
+        .unified_diff()
+        .context_radius(CONTEXT_RADIUS)
+        .iter_hunks()
+        .enumerate()
+    {
+        if index == 0 {
+            let header = format!("--- {path}\n+++ {path}");
+            let _ = writeln!(
+                output,
+                "{}",
+                paint(&header, colored, |s| s.bold().to_string())
+            );
+        }
+
+        let hunk_header = hunk.header().to_string();
+        let _ = writeln!(
+            output,
+            "{}",
+            paint(&hunk_header, colored, |s| s.cyan().to_string())
+        );
+
+        for change in hunk.iter_changes() {
+            let value = change.value();
+            let value = value.strip_suffix('\n').unwrap_or(value);
+            let marked;
+            let changed_value = match value.strip_suffix('\r') {
+                Some(text) if colored => {
+                    marked = format!("{text}{CR_MARKER}");
+                    &marked
+                }
+                _ => value,
+            };
+            let line = match change.tag() {
+                ChangeTag::Delete => paint(&format!("-{changed_value}"), colored, |s| {
+                    s.red().to_string()
+                }),
+                ChangeTag::Insert => paint(&format!("+{changed_value}"), colored, |s| {
+                    s.green().to_string()
+                }),
+                ChangeTag::Equal => format!(" {value}"),
+            };
+            let _ = writeln!(output, "{line}");
+
+            if change.missing_newline() {
+                output.push_str("\\ No newline at end of file\n");
+            }
+        }
+    }
+
+    output
+}
+
+fn paint(text: &str, colored: bool, style: impl Fn(&str) -> String) -> String {
+    if colored {
+        style(text)
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unified_diff;
+
+    #[test]
+    fn test_unified_diff_identical() {
+        assert!(unified_diff("a.ets", "let a = 1\n", "let a = 1\n", false).is_empty());
+    }
+
+    #[test]
+    fn test_unified_diff_changed_line() {
+        let diff = unified_diff(
+            "entry/src/main/ets/pages/Index.ets",
+            "let a = 1\nlet b  =  2\nlet c = 3\n",
+            "let a = 1\nlet b = 2\nlet c = 3\n",
+            false,
+        );
+
+        assert_eq!(
+            diff,
+            "--- entry/src/main/ets/pages/Index.ets\n\
+             +++ entry/src/main/ets/pages/Index.ets\n\
+             @@ -1,3 +1,3 @@\n \
+             let a = 1\n\
+             -let b  =  2\n\
+             +let b = 2\n \
+             let c = 3\n"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_missing_newline() {
+        let diff = unified_diff("module.json5", "{}", "{}\n", false);
+        assert!(diff.contains("-{}\n\\ No newline at end of file\n+{}\n"));
+    }
+
+    #[test]
+    fn test_unified_diff_line_endings() {
+        let diff = unified_diff(
+            "Index.ets",
+            "let a = 1\r\nlet b = 2\r\n",
+            "let a = 1\nlet b = 2\n",
+            false,
+        );
+        assert!(diff.ends_with("-let a = 1\r\n-let b = 2\r\n+let a = 1\n+let b = 2\n"));
+
+        let diff = unified_diff("Index.ets", "let a = 1\r\n", "let a = 1\n", true);
+        assert!(diff.contains("-let a = 1␍"));
+        assert!(!diff.contains('\r'));
+    }
+}
@@ -0,0 +1,516 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use format::{FormatFileStrategy, should_ignore_file};
+use ignore::{Match, gitignore::Gitignore};
+
+use super::{FileError, FileStatus, FormatContext, diff, display_path, report::FileReport};
+
+/// Restrict the formatted files to what git reports as changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitFilter {
+    /// Files with staged changes, formatted in the index.
+    Staged,
+    /// Files with staged or unstaged changes, and untracked files.
+    Changed,
+    /// Files changed since a revision, and untracked files.
+    Since(String),
+}
+
+impl GitFilter {
+    pub fn from_args(args: &crate::FormatArgs) -> Result<Option<Self>, String> {
+        match (args.staged, args.changed, &args.since) {
+            (false, false, None) => Ok(None),
+            (true, false, None) => Ok(Some(Self::Staged)),
+            (false, true, None) => Ok(Some(Self::Changed)),
+            (false, false, Some(rev)) => Ok(Some(Self::Since(rev.clone()))),
+            _ => Err("Only one of `--staged`, `--changed` and `--since` can be used".to_string()),
+        }
+    }
+}
+
+/// Top-level directory of the git repository containing `cwd`.
+pub fn repository_root(cwd: &Path) -> Result<PathBuf, String> {
+    let output = git(cwd, &["rev-parse", "--show-toplevel"], None)?;
+    let root = String::from_utf8_lossy(&output).trim_end().to_string();
+    Ok(super::normalize_path(Path::new(&root)).unwrap_or_else(|_| PathBuf::from(root)))
+}
+
+/// List existing files selected by `filter`, as absolute paths.
+pub fn list_files(root: &Path, filter: &GitFilter) -> Result<HashSet<PathBuf>, String> {
+    let staged = [
+        "diff",
+        "--cached",
+        "--name-only",
+        "-z",
+        "--diff-filter=ACMR",
+    ];
+    let untracked = ["ls-files", "-z", "--others", "--exclude-standard"];
+
+    let outputs = match filter {
+        GitFilter::Staged => vec![git(root, &staged, None)?],
+        GitFilter::Changed => vec![
+            git(root, &staged, None)?,
+            git(root, &["ls-files", "-z", "--modified"], None)?,
+            git(root, &untracked, None)?,
+        ],
+        GitFilter::Since(rev) => vec![
+            git(
+                root,
+                &["diff", "--name-only", "-z", "--diff-filter=ACMR", rev, "--"],
+                None,
+            )?,
+            git(root, &untracked, None)?,
+        ],
+    };
+
+    Ok(outputs
+        .iter()
+        .flat_map(|output| split_nul(output))
+        .map(|path| root.join(path))
+        // Deleted files are listed as modified as well
+        .filter(|path| path.is_file())
+        .collect())
+}
+
+/// Select the files reported by git that match `patterns`, or every supported file without any.
+/// Ignore files and default ignored directories are honored as in a walk of the repository.
+pub fn select_files(
+    root: &Path,
+    git_files: &HashSet<PathBuf>,
+    patterns: &[String],
+    cwd: &Path,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let (negated_patterns, patterns): (Vec<_>, Vec<_>) = patterns
+        .iter()
+        .cloned()
+        .partition(|pattern| super::is_negated(pattern));
+    let negated_patterns: Vec<_> = negated_patterns
+        .iter()
+        .map(|pattern| pattern[1..].to_string())
+        .collect();
+    let matcher = super::build_globset(&patterns, cwd)?;
+    let negated_matcher = super::build_globset(&negated_patterns, cwd)?;
+    let pattern_roots = match &matcher {
+        Some(_) => patterns
+            .iter()
+            .map(|pattern| super::determine_root(&super::to_absolute_pattern(pattern, cwd), cwd))
+            .collect(),
+        None => vec![root.to_path_buf()],
+    };
+    let cwd = super::normalize_path(cwd)?;
+
+    let mut ignore_files = HashMap::new();
+    let mut files: Vec<_> = git_files
+        .iter()
+        .filter(|path| {
+            let path_str = path.to_string_lossy();
+            let is_match = match &matcher {
+                Some(matcher) => matcher.is_match(path_str.as_ref()),
+                None => FormatFileStrategy::try_from(path.to_path_buf())
+                    .is_ok_and(|strategy| super::is_supported_strategy(&strategy)),
+            };
+            is_match
+                && !negated_matcher
+                    .as_ref()
+                    .is_some_and(|matcher| matcher.is_match(path_str.as_ref()))
+                && pattern_roots
+                    .iter()
+                    .any(|pattern_root| !is_in_default_ignored_dir(path, pattern_root))
+                && !is_ignored(path, root, &mut ignore_files)
+        })
+        .filter_map(|path| {
+            let normalized = super::normalize_path(path).ok()?;
+            (!super::escapes_working_dir(path, &normalized, &cwd)).then_some(normalized)
+        })
+        .collect();
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Whether `path` is below a default ignored directory inside `pattern_root`,
+/// which a walk from `pattern_root` would not traverse.
+fn is_in_default_ignored_dir(path: &Path, pattern_root: &Path) -> bool {
+    let Ok(relative) = path.strip_prefix(pattern_root) else {
+        return true;
+    };
+    relative.parent().is_some_and(|dir| {
+        dir.components().any(|component| {
+            component
+                .as_os_str()
+                .to_str()
+                .is_some_and(|name| super::DEFAULT_IGNORE_DIRS.contains(&name))
+        })
+    })
+}
+
+/// Whether `path` is ignored by an ignore file in its directory or an ancestor up to `root`.
+///
+/// Tracked files are listed by git even if `.gitignore` matches them, a walk skips them.
+/// Parsed ignore files are cached per directory in `ignore_files`.
+fn is_ignored(
+    path: &Path,
+    root: &Path,
+    ignore_files: &mut HashMap<PathBuf, Vec<Gitignore>>,
+) -> bool {
+    let dirs = path
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(root));
+    // Nearer directories and later files take precedence
+    for dir in dirs {
+        let matchers = ignore_files.entry(dir.to_path_buf()).or_insert_with(|| {
+            std::iter::once(".gitignore")
+                .chain(super::IGNORE_FILENAMES)
+                .map(|filename| dir.join(filename))
+                .filter(|path| path.is_file())
+                .map(|path| Gitignore::new(path).0)
+                .collect()
+        });
+        for matcher in matchers.iter().rev() {
+            match matcher.matched_path_or_any_parents(path, false) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+    }
+    false
+}
+
+/// Format the staged content of each file and stage the result.
+///
+/// The formatting is applied to the working tree as well, unless it conflicts with unstaged changes.
+pub fn format_staged(root: &Path, files: Vec<PathBuf>, context: &FormatContext) -> Vec<FileReport> {
+    let mut reports = Vec::new();
+    for path in files {
+        let report = match format_staged_file(root, &path, context) {
+            Ok(status) => FileReport::new(path, status),
+            Err(err) => err.into_report(path),
+        };
+        let failed = report.status == FileStatus::Failed;
+        reports.push(report);
+        if failed && context.format_args.fail_fast {
+            break;
+        }
+    }
+    reports
+}
+
+fn format_staged_file(
+    root: &Path,
+    path: &Path,
+    context: &FormatContext,
+) -> Result<FileStatus, FileError> {
+    let format_args = &context.format_args;
+    // Git paths always use `/`
+    let git_path = path
+        .strip_prefix(root)
+        .map_err(|_| format!("File '{}' is outside of the repository", path.display()))?
+        .to_string_lossy()
+        .replace('\\', "/");
+
+    let entry = git(root, &["ls-files", "-s", "-z", "--", &git_path], None)?;
+    let Some((mode, blob_id)) = split_nul(&entry).next().and_then(parse_index_entry) else {
+        return Ok(FileStatus::Skipped);
+    };
+
+    let blob = git(root, &["cat-file", "blob", &blob_id], None)?;
+    if blob.is_empty() || should_ignore_file(path) {
+        return Ok(FileStatus::Skipped);
+    }
+    let Ok(strategy) = FormatFileStrategy::try_from(path.to_path_buf()) else {
+        return Ok(FileStatus::Unsupported);
+    };
+    if !super::is_supported_strategy(&strategy) {
+        return Ok(FileStatus::Unsupported);
+    }
+    let source_text = super::decode_source(blob.clone(), format_args.encoding)?;
+    let resolved_options =
+        super::resolve_options(&strategy, &context.config_resolver, &source_text)?;
+
+    let (formatted_code, is_changed) = match super::format_source(
+        &context.formatter,
+        &strategy,
+        &source_text,
+        resolved_options,
+    ) {
+        Ok(result) => result,
+        Err(diagnostics) => {
+            return Err(FileError::Parse {
+                source_text,
+                diagnostics,
+            });
+        }
+    };
+    if !is_changed {
+        return Ok(FileStatus::Unchanged);
+    }
+
+    if format_args.diff {
+        let colored = super::use_color(format_args, std::io::stdout().is_terminal());
+        print!(
+            "{}",
+            diff::unified_diff(&display_path(path), &source_text, &formatted_code, colored)
+        );
+    }
+    if format_args.check || format_args.diff {
+        return Ok(FileStatus::Changed);
+    }
+
+    // Write the formatted blob and point the index at it, keeping the file mode
+    let formatted_bytes = super::encode_source(&formatted_code, format_args.encoding)?;
+    let formatted_id = git(
+        root,
+        &["hash-object", "-w", "--stdin", "--path", &git_path],
+        Some(&formatted_bytes),
+    )?;
+    let formatted_id = String::from_utf8_lossy(&formatted_id).trim().to_string();
+    let cache_info = format!("{mode},{formatted_id},{git_path}");
+    git(root, &["update-index", "--cacheinfo", &cache_info], None)?;
+
+    // Apply the same change to the working tree, keeping unstaged hunks
+    let worktree = std::fs::read(path).unwrap_or_default();
+    if worktree == blob {
+        super::write::write_atomic(path, &formatted_bytes)
+            .map_err(|e| format!("Failed to write to '{}': {}", path.display(), e))?;
+    } else {
+        let patch = diff::unified_diff(&git_path, &source_text, &formatted_code, false);
+        let patch = super::encode_source(&patch, format_args.encoding)?;
+        if git(root, &["apply", "-p0", "-"], Some(&patch)).is_err() {
+            eprintln!(
+                "Warning: {}: Formatting conflicts with unstaged changes, only the index was updated",
+                display_path(path)
+            );
+        }
+    }
+
+    Ok(FileStatus::Changed)
+}
+
+/// Parse `<mode> <object> <stage>\t<path>` from `git ls-files -s`.
+fn parse_index_entry(entry: &str) -> Option<(String, String)> {
+    let (info, _path) = entry.split_once('\t')?;
+    let mut parts = info.split(' ');
+    Some((parts.next()?.to_string(), parts.next()?.to_string()))
+}
+
+fn split_nul(output: &[u8]) -> impl Iterator<Item = &str> {
+    output
+        .split(|&b| b == 0)
+        .filter(|s| !s.is_empty())
+        .filter_map(|s| std::str::from_utf8(s).ok())
+}
+
+/// Run `git` in `dir` and return its stdout.
+fn git(dir: &Path, args: &[&str], input: Option<&[u8]>) -> Result<Vec<u8>, String> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin
+            .write_all(input)
+            .map_err(|e| format!("Failed to write to git: {}", e))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "`git {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, path::Path};
+
+    use super::{GitFilter, format_staged, git, list_files, repository_root, select_files};
+    use crate::format::test_utils::TempDir;
+    use crate::format::{FileStatus, FormatContext};
+
+    fn init_repo(name: &str) -> TempDir {
+        let dir = TempDir::new(&format!("git-{name}"));
+        git(dir.path(), &["init", "-q"], None).expect("Failed to init repository");
+        dir
+    }
+
+    fn run(dir: &Path, args: &[&str]) -> String {
+        String::from_utf8(git(dir, args, None).expect("git should succeed")).expect("UTF-8 output")
+    }
+
+    #[test]
+    fn test_list_changed_files() {
+        let dir = init_repo("changed");
+        std::fs::write(dir.path().join("committed.ts"), "const a = 1;\n")
+            .expect("Failed to write temp file");
+        run(dir.path(), &["add", "."]);
+        run(
+            dir.path(),
+            &[
+                "-c",
+                "user.name=oxk",
+                "-c",
+                "user.email=oxk@example.com",
+                "commit",
+                "-qm",
+                "init",
+            ],
+        );
+        std::fs::write(dir.path().join("staged.ts"), "const b = 1;\n")
+            .expect("Failed to write temp file");
+        run(dir.path(), &["add", "staged.ts"]);
+        std::fs::write(dir.path().join("untracked.ts"), "const c = 1;\n")
+            .expect("Failed to write temp file");
+
+        let root = repository_root(dir.path()).expect("Repository root");
+        let mut staged: Vec<_> = list_files(&root, &GitFilter::Staged)
+            .expect("Staged files")
+            .into_iter()
+            .collect();
+        staged.sort();
+        assert_eq!(staged, [root.join("staged.ts")]);
+
+        let mut changed: Vec<_> = list_files(&root, &GitFilter::Since("HEAD".to_string()))
+            .expect("Changed files")
+            .into_iter()
+            .collect();
+        changed.sort();
+        assert_eq!(changed, [root.join("staged.ts"), root.join("untracked.ts")]);
+    }
+
+    #[test]
+    fn test_select_files_honors_ignore_files() {
+        let dir = TempDir::new("git-select");
+        std::fs::write(dir.path().join(".gitignore"), "build/\n")
+            .expect("Failed to write temp file");
+        std::fs::write(dir.path().join(".oxkignore"), "ignored/\n")
+            .expect("Failed to write temp file");
+        let files = [
+            "a.ts",
+            "notes.txt",
+            "build/b.ts",
+            "ignored/c.ts",
+            "oh_modules/d.ts",
+        ];
+        for file in files {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().expect("Parent directory"))
+                .expect("Failed to create temp dir");
+            std::fs::write(path, "const a = 1;\n").expect("Failed to write temp file");
+        }
+        let root = dir
+            .path()
+            .canonicalize()
+            .expect("Failed to canonicalize temp dir");
+        let git_files: HashSet<_> = files.iter().map(|file| root.join(file)).collect();
+
+        let selected = select_files(&root, &git_files, &[], &root).expect("Selected files");
+        assert_eq!(selected, [root.join("a.ts")]);
+
+        // A pattern rooted inside a default ignored directory still matches
+        let pattern = "oh_modules/**/*.ts".to_string();
+        let selected = select_files(&root, &git_files, &[pattern], &root).expect("Selected files");
+        assert_eq!(selected, [root.join("oh_modules/d.ts")]);
+
+        let patterns = ["**/*".to_string(), "!**/*.ts".to_string()];
+        let selected = select_files(&root, &git_files, &patterns, &root).expect("Selected files");
+        assert_eq!(selected, [root.join("notes.txt")]);
+    }
+
+    #[test]
+    fn test_format_staged_keeps_unstaged_hunks() {
+        let dir = init_repo("staged");
+        let lines: Vec<_> = ('b'..='k')
+            .map(|key| format!("  \"{key}\": 1,\n"))
+            .collect();
+        let staged = format!("{{\n  \"a\":   1,\n{}}}\n", lines.concat());
+        std::fs::write(dir.path().join("module.json5"), &staged)
+            .expect("Failed to write temp file");
+        run(dir.path(), &["add", "module.json5"]);
+        // An unstaged change that does not overlap with the formatting
+        std::fs::write(
+            dir.path().join("module.json5"),
+            staged.replace("\"f\": 1", "\"f\": 2"),
+        )
+        .expect("Failed to write temp file");
+
+        let root = repository_root(dir.path()).expect("Repository root");
+        let args = crate::FormatArgs::default();
+        let (config_resolver, _) =
+            crate::format::load_config(&root, &args).expect("Config should load");
+        let context = FormatContext::new(config_resolver, None, args);
+        let reports = format_staged(&root, vec![root.join("module.json5")], &context);
+        assert_eq!(reports[0].status, FileStatus::Changed);
+
+        let formatted = run(dir.path(), &["show", ":module.json5"]);
+        assert!(formatted.starts_with("{\n  \"a\": 1,\n"));
+        assert!(formatted.contains("\"f\": 1"));
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("module.json5"))
+                .expect("Failed to read temp file"),
+            formatted.replace("\"f\": 1", "\"f\": 2")
+        );
+    }
+
+    #[test]
+    fn test_format_staged_keeps_unstaged_hunks_with_crlf() {
+        let dir = init_repo("staged-crlf");
+        std::fs::write(
+            dir.path().join(".oxfmtrc.json"),
+            r#"{ "endOfLine": "auto" }"#,
+        )
+        .expect("Failed to write temp file");
+        let lines: Vec<_> = ('b'..='k')
+            .map(|key| format!("  \"{key}\": 1,\r\n"))
+            .collect();
+        let staged = format!("{{\r\n  \"a\":   1,\r\n{}}}", lines.concat());
+        std::fs::write(dir.path().join("module.json5"), &staged)
+            .expect("Failed to write temp file");
+        run(dir.path(), &["add", "module.json5"]);
+        std::fs::write(
+            dir.path().join("module.json5"),
+            staged.replace("\"f\": 1", "\"f\": 2"),
+        )
+        .expect("Failed to write temp file");
+
+        let root = repository_root(dir.path()).expect("Repository root");
+        let args = crate::FormatArgs::default();
+        let (config_resolver, _) =
+            crate::format::load_config(&root, &args).expect("Config should load");
+        let context = FormatContext::new(config_resolver, None, args);
+        let reports = format_staged(&root, vec![root.join("module.json5")], &context);
+        assert_eq!(reports[0].status, FileStatus::Changed);
+
+        // The patch applied to the working tree matches its CRLF lines and missing final newline
+        let formatted = run(dir.path(), &["show", ":module.json5"]);
+        assert!(formatted.starts_with("{\r\n  \"a\": 1,\r\n"));
+        assert!(formatted.ends_with("}\r\n"));
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("module.json5"))
+                .expect("Failed to read temp file"),
+            formatted.replace("\"f\": 1", "\"f\": 2")
+        );
+    }
+}
@@ -1,33 +1,272 @@
+mod cache;
+mod diff;
+mod git;
+mod report;
+#[cfg(test)]
+mod test_utils;
+mod watch;
+mod write;
+
 use std::{
+    borrow::Cow,
     collections::HashSet,
     env,
+    io::{IsTerminal, Read, Write},
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
+use cache::FormatCache;
+use encoding_rs::Encoding;
 use format::{
-    ConfigResolver, FormatFileStrategy, ResolvedOptions, SourceFormatter, should_ignore_file,
+    ConfigResolver, EndOfLine, FormatFileStrategy, ResolvedOptions, SourceFormatter, decode_utf8,
+    resolve_oxfmtrc_path, should_ignore_file,
 };
-use futures::future;
 use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::{WalkBuilder, WalkState};
+use oxc_diagnostics::OxcDiagnostic;
 use oxc_formatter::FormatOptions;
-use serde_json::Value;
-use tokio::sync::Semaphore;
-use walkdir::WalkDir;
+use report::{FileReport, Summary};
+use tokio::{sync::Semaphore, task::JoinSet};
+
+pub use report::Reporter;
+
+/// Exit status returned by `--check` and `--diff` when some files are not formatted.
+pub const EXIT_CODE_UNFORMATTED: i32 = 1;
+
+/// Exit status returned when some files could not be read, parsed or written.
+pub const EXIT_CODE_FAILED: i32 = 2;
+
+/// Gitignore-style files honored in every directory, in addition to `.gitignore`.
+/// Later files take precedence over earlier ones.
+const IGNORE_FILENAMES: [&str; 2] = [".prettierignore", ".oxkignore"];
+
+/// Dependency and build output directories that are never traversed.
+const DEFAULT_IGNORE_DIRS: [&str; 6] = [
+    "oh_modules",
+    "build",
+    ".hvigor",
+    ".preview",
+    "node_modules",
+    ".git",
+];
+
+/// UTF-8 byte-order mark, preserved on files that start with one.
+const BOM: &str = "\u{feff}";
+
+/// Files read or written ahead of formatting, per `--thread`.
+const FILES_IN_FLIGHT_PER_THREAD: usize = 2;
+
+/// Outcome of formatting a single file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileStatus {
+    /// The formatted output differs from the original source.
+    Changed,
+    /// The file is already formatted.
+    Unchanged,
+    /// The file was skipped (empty or ignored).
+    Skipped,
+    /// The file type is not supported by the CLI.
+    Unsupported,
+    /// The file could not be read, parsed or written.
+    Failed,
+}
+
+/// Error while formatting a single file.
+#[derive(Debug)]
+enum FileError {
+    /// The source could not be parsed, with diagnostics pointing into it.
+    Parse {
+        source_text: String,
+        diagnostics: Vec<OxcDiagnostic>,
+    },
+    /// Any other error, e.g. failing to read or write the file.
+    Other(String),
+}
 
-pub fn format(args: crate::FormatArgs) -> Result<(), Box<dyn std::error::Error>> {
-    let patterns = args.file.clone();
+impl FileError {
+    fn into_report(self, path: PathBuf) -> FileReport {
+        let mut report = FileReport::new(path, FileStatus::Failed);
+        match self {
+            Self::Parse {
+                source_text,
+                diagnostics,
+            } => {
+                // Diagnostics point into the source without its byte-order mark
+                report.source_text = Some(split_bom(&source_text).1.to_string());
+                report.diagnostics = diagnostics;
+            }
+            Self::Other(message) => report.diagnostics = vec![OxcDiagnostic::error(message)],
+        }
+        report
+    }
+}
+
+impl std::fmt::Display for FileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse { diagnostics, .. } => {
+                write!(f, "Parser errors:")?;
+                for diagnostic in diagnostics {
+                    write!(f, "\n  {diagnostic}")?;
+                }
+                Ok(())
+            }
+            Self::Other(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl From<String> for FileError {
+    fn from(message: String) -> Self {
+        Self::Other(message)
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for FileError {
+    fn from(err: Box<dyn std::error::Error>) -> Self {
+        Self::Other(err.to_string())
+    }
+}
+
+/// State shared by all files formatted in a run.
+struct FormatContext {
+    config_resolver: ConfigResolver,
+    /// Shared by all files, with an allocator per `--thread`.
+    formatter: SourceFormatter,
+    /// Limits parsing and printing to `--thread` files at a time.
+    cpu_permits: Semaphore,
+    cache: Option<FormatCache>,
+    format_args: crate::FormatArgs,
+}
+
+impl FormatContext {
+    fn new(
+        config_resolver: ConfigResolver,
+        cache: Option<FormatCache>,
+        format_args: crate::FormatArgs,
+    ) -> Self {
+        let thread_count = format_args.thread.max(1);
+        Self {
+            config_resolver,
+            formatter: SourceFormatter::new(thread_count),
+            cpu_permits: Semaphore::new(thread_count),
+            cache,
+            format_args,
+        }
+    }
+}
+
+pub fn format(mut args: crate::FormatArgs) -> Result<i32, Box<dyn std::error::Error>> {
     let thread_count = args.thread;
-    let excludes = args.excludes.clone();
-    let format_options = args.clone();
+    let check = args.check || args.diff;
+    let show_diff = args.diff;
+    let reporter = args.reporter;
+
+    let cwd = env::current_dir()?;
+    let (config_resolver, mut excludes) = load_config(&cwd, &args)?;
+    let patterns = anchor_file_patterns(&cwd, &args);
+    args.file = patterns.clone();
+    let git_filter = git::GitFilter::from_args(&args)?;
+
+    // Reports are written once all files are formatted, which never happens for stdin or watch mode
+    if reporter != Reporter::Default && (show_diff || args.stdin_filepath.is_some() || args.watch) {
+        return Err(
+            "`--reporter` cannot be combined with `--diff`, `--stdin-filepath` or `--watch`".into(),
+        );
+    }
 
-    if patterns.is_empty() {
+    if let Some(filepath) = &args.stdin_filepath {
+        return format_stdin(filepath, &config_resolver, &args);
+    }
+
+    let listed_files = match &args.files_from {
+        Some(source) => read_file_list(source, args.null_separated)?,
+        None => Vec::new(),
+    };
+
+    let has_patterns = patterns.iter().any(|pattern| !is_negated(pattern));
+    if !has_patterns && args.files_from.is_none() && git_filter.is_none() {
         return Err(Box::new(std::io::Error::other("Missing file pattern")));
     }
 
+    // Watch mode and staged content are formatted without the cache of the working tree
+    if (args.cache || args.cache_location.is_some())
+        && (args.watch || git_filter == Some(git::GitFilter::Staged))
+    {
+        return Err("`--cache` cannot be combined with `--watch` or `--staged`".into());
+    }
+
+    if let Some(out_dir) = &args.out_dir {
+        if check
+            || git_filter == Some(git::GitFilter::Staged)
+            || args.cache
+            || args.cache_location.is_some()
+        {
+            return Err(
+                "`--out-dir` cannot be combined with `--check`, `--diff`, `--staged` or `--cache`"
+                    .into(),
+            );
+        }
+        let out_dir = cwd.join(out_dir);
+        // Formatted copies are never formatted again as inputs
+        excludes.push(format!(
+            "{}/**",
+            globset::escape(&out_dir.to_string_lossy())
+        ));
+        args.out_dir = Some(out_dir);
+    }
+
+    if args.watch {
+        // Watching would pick up the formatted copies of `--out-dir` as new changes
+        if check || git_filter.is_some() || args.files_from.is_some() || args.out_dir.is_some() {
+            return Err(
+                "`--watch` cannot be combined with `--check`, `--diff`, `--files-from`, `--out-dir` or git filters"
+                    .into(),
+            );
+        }
+        return watch::watch(&cwd, &args);
+    }
+
+    let cache = match &args.cache_location {
+        Some(location) => Some(FormatCache::load(location)),
+        None if args.cache => Some(FormatCache::load(&cwd.join(cache::DEFAULT_CACHE_LOCATION))),
+        None => None,
+    };
+    let context = Arc::new(FormatContext::new(config_resolver, cache, args.clone()));
+
     // Collect matching files (handles both exact paths and glob patterns)
-    let exclude_matcher = build_globset(&excludes)?;
-    let mut files = collect_matching_files(&patterns)?;
+    let exclude_matcher = build_globset(&excludes, &cwd)?;
+    let (mut files, git_root) = match &git_filter {
+        Some(filter) => {
+            let root = git::repository_root(&cwd)?;
+            let git_files = git::list_files(&root, filter)?;
+            // Without patterns, every supported file reported by git is formatted
+            let mut files = if has_patterns || args.files_from.is_none() {
+                git::select_files(&root, &git_files, &patterns, &cwd)?
+            } else {
+                Vec::new()
+            };
+            for path in
+                collect_input_files(&[], &listed_files, &cwd, args.follow_symlinks, args.thread)?
+            {
+                if git_files.contains(&path) && !files.contains(&path) {
+                    files.push(path);
+                }
+            }
+            (files, Some(root))
+        }
+        None => (
+            collect_input_files(
+                &patterns,
+                &listed_files,
+                &cwd,
+                args.follow_symlinks,
+                args.thread,
+            )?,
+            None,
+        ),
+    };
 
     // Remove files that match any exclude pattern
     if let Some(matcher) = exclude_matcher {
@@ -35,11 +274,26 @@ pub fn format(args: crate::FormatArgs) -> Result<(), Box<dyn std::error::Error>>
     }
 
     if files.is_empty() {
+        // Having no changed files is not an error in git mode
+        if git_filter.is_some() {
+            return Ok(0);
+        }
         return Err(Box::new(std::io::Error::other(
             "No files matched the provided patterns (after excludes)",
         )));
     }
 
+    if let (Some(git::GitFilter::Staged), Some(root)) = (&git_filter, &git_root) {
+        let reports = git::format_staged(root, files, &context);
+        if reporter == Reporter::Default {
+            let color = use_color(&args, std::io::stderr().is_terminal());
+            for report in &reports {
+                print_file_report(report, color);
+            }
+        }
+        return Ok(report_results(&reports, &args));
+    }
+
     // Create tokio runtime with thread pool size based on thread_count
     let runtime = tokio::runtime::Builder::new_multi_thread()
         .worker_threads(thread_count)
@@ -52,143 +306,308 @@ pub fn format(args: crate::FormatArgs) -> Result<(), Box<dyn std::error::Error>>
             ))) as Box<dyn std::error::Error>
         })?;
 
-    // Execute async code in the runtime
-    runtime.block_on(async {
-        // Create a Semaphore to limit concurrent tasks based on thread_count
-        let semaphore = Arc::new(Semaphore::new(thread_count));
+    let result = runtime
+        .block_on(format_files(context.clone(), files))
+        .map(|reports| report_results(&reports, &args));
 
-        // Spawn a tokio task for each file path
-        let mut handles = Vec::new();
+    // Files formatted before an error are still cached
+    if let Some(cache) = &context.cache
+        && let Err(err) = cache.save()
+    {
+        eprintln!("Warning: {}", err);
+    }
 
-        for path in files {
-            let semaphore = semaphore.clone();
-            let path = path.clone();
-            let format_options = format_options.clone();
+    result.map_err(|err| Box::new(std::io::Error::other(err)) as Box<dyn std::error::Error>)
+}
 
-            // Spawn format_file as a tokio task
-            let handle =
-                tokio::spawn(
-                    async move { format_file_task(path, semaphore, format_options).await },
-                );
-            handles.push(handle);
+/// Format files on a bounded set of tokio tasks and collect their reports.
+///
+/// Only a few files per thread are in flight at once, so reading and writing files overlaps
+/// with formatting without holding the whole repository in memory.
+async fn format_files(
+    context: Arc<FormatContext>,
+    files: Vec<PathBuf>,
+) -> Result<Vec<FileReport>, String> {
+    let format_args = &context.format_args;
+    let print_reports = format_args.reporter == Reporter::Default;
+    let color = use_color(format_args, std::io::stderr().is_terminal());
+    let max_in_flight = format_args.thread.max(1) * FILES_IN_FLIGHT_PER_THREAD;
+
+    let mut files = files.into_iter();
+    let mut tasks = JoinSet::new();
+    let mut reports = Vec::new();
+    let mut task_panic = None;
+
+    loop {
+        while tasks.len() < max_in_flight
+            && let Some(path) = files.next()
+        {
+            tasks.spawn(format_file_task(path, context.clone()));
         }
+        let Some(result) = tasks.join_next().await else {
+            break;
+        };
 
-        // Wait for tasks to complete concurrently
-        let mut ast_parse_error = None;
-        let mut remaining_handles = handles;
-
-        while !remaining_handles.is_empty() {
-            // Select the first completed task
-            let (result, _index, remaining) = future::select_all(remaining_handles).await;
-
-            match result {
-                Ok(Ok(())) => {
-                    // Task completed successfully, continue with remaining tasks
-                    remaining_handles = remaining;
+        match result {
+            Ok(report) => {
+                if print_reports {
+                    print_file_report(&report, color);
                 }
-                Ok(Err(err)) => {
-                    // Check if this is an AST parse error
-                    if err.starts_with("AST_PARSE_ERROR:") {
-                        // AST parse error: abort all remaining tasks and exit immediately
-                        ast_parse_error = Some(err);
-                        // Abort all remaining tasks
-                        for handle in remaining {
-                            handle.abort();
-                        }
-                        remaining_handles = Vec::new();
-                        break;
-                    } else {
-                        // Non-AST error: print warning and continue processing
-                        eprintln!("Warning: {}", err);
-                        remaining_handles = remaining;
-                    }
-                }
-                Err(e) => {
-                    // Task panicked: treat as fatal error
-                    ast_parse_error = Some(format!("Task panicked: {:?}", e));
-                    // Abort all remaining tasks
-                    for handle in remaining {
-                        handle.abort();
-                    }
-                    remaining_handles = Vec::new();
+                let failed = report.status == FileStatus::Failed;
+                reports.push(report);
+                if failed && format_args.fail_fast {
                     break;
                 }
             }
+            Err(e) => {
+                // Task panicked: treat as fatal error
+                task_panic = Some(format!("Task panicked: {:?}", e));
+                break;
+            }
         }
+    }
 
-        // Wait for all remaining tasks to finish (including aborted ones)
-        for handle in remaining_handles {
-            let _ = handle.await;
-        }
-
-        // Return error only if AST parse error occurred
-        if let Some(err) = ast_parse_error {
-            // Remove the prefix when returning the error
-            let error_msg = if err.starts_with("AST_PARSE_ERROR:") {
-                err.strip_prefix("AST_PARSE_ERROR: ")
-                    .unwrap_or(&err)
-                    .to_string()
-            } else {
-                err
-            };
-            return Err(Box::new(std::io::Error::other(error_msg)) as Box<dyn std::error::Error>);
+    // Abort the remaining tasks, those completed before being aborted still count
+    tasks.abort_all();
+    while let Some(result) = tasks.join_next().await {
+        if let Ok(report) = result {
+            if print_reports {
+                print_file_report(&report, color);
+            }
+            reports.push(report);
         }
+    }
 
-        Ok(())
-    })
+    match task_panic {
+        Some(err) => Err(err),
+        None => Ok(reports),
+    }
 }
 
-fn collect_matching_files(patterns: &[String]) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
-    let mut seen = HashSet::new();
-    let mut files = Vec::new();
-
-    for pattern in patterns {
-        // Convert pattern to absolute path
-        let absolute_pattern = to_absolute_pattern(pattern)?;
-
-        // Build globset matcher
-        let glob = Glob::new(&absolute_pattern)
-            .map_err(|e| format!("Invalid glob pattern '{}': {}", pattern, e))?;
-        let glob_set = GlobSetBuilder::new()
-            .add(glob)
-            .build()
-            .map_err(|e| format!("Failed to build glob set: {}", e))?;
-
-        // Determine root directory for traversal
-        let root = determine_root(&absolute_pattern)?;
-
-        // Traverse directory tree and match files
-        for entry in WalkDir::new(&root).follow_links(false) {
-            match entry {
-                Ok(entry) if entry.file_type().is_file() => {
-                    let path = entry.path();
-                    let path_str = path.to_string_lossy();
-
-                    if glob_set.is_match(path_str.as_ref()) {
-                        let normalized = normalize_path(path)?;
-                        let key = normalized.to_string_lossy().into_owned();
-                        if seen.insert(key) {
-                            files.push(normalized);
+/// Collect files matching any of the glob `patterns`, except those matching a `!pattern`.
+///
+/// Relative patterns are relative to `cwd`, the current directory. All patterns are matched in
+/// a single walk on `threads` threads. Symlinked directories and symlinks outside of `cwd` are
+/// only followed with `follow_symlinks`.
+fn collect_matching_files(
+    patterns: &[String],
+    cwd: &Path,
+    follow_symlinks: bool,
+    threads: usize,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let (negated_patterns, patterns): (Vec<_>, Vec<_>) = patterns
+        .iter()
+        .cloned()
+        .partition(|pattern| is_negated(pattern));
+    let negated_patterns: Vec<_> = negated_patterns
+        .iter()
+        .map(|pattern| pattern[1..].to_string())
+        .collect();
+    let Some(matcher) = build_globset(&patterns, cwd)? else {
+        return Ok(Vec::new());
+    };
+    let negated_matcher = build_globset(&negated_patterns, cwd)?;
+
+    let mut roots = patterns
+        .iter()
+        .map(|pattern| determine_root(&to_absolute_pattern(pattern, cwd), cwd))
+        .collect::<Vec<_>>();
+    roots.sort();
+    roots.dedup();
+
+    let cwd = normalize_path(cwd)?;
+
+    let files = Mutex::new(Vec::new());
+    build_walker(&roots, follow_symlinks, threads)
+        .build_parallel()
+        .run(|| {
+            let (files, matcher, negated_matcher, cwd) = (&files, &matcher, &negated_matcher, &cwd);
+            Box::new(move |entry| {
+                match entry {
+                    Ok(entry) if entry.file_type().is_some_and(|t| t.is_file()) => {
+                        let path = entry.path();
+                        let path_str = path.to_string_lossy();
+                        if matcher.is_match(path_str.as_ref())
+                            && !negated_matcher
+                                .as_ref()
+                                .is_some_and(|matcher| matcher.is_match(path_str.as_ref()))
+                            && let Ok(normalized) = normalize_path(path)
+                            && !escapes_working_dir(path, &normalized, cwd)
+                        {
+                            // A panic in another walker thread must not fail this one as well
+                            files
+                                .lock()
+                                .unwrap_or_else(|e| e.into_inner())
+                                .push(normalized);
                         }
                     }
+                    // Includes symlink loops found with `follow_symlinks`
+                    Err(e) => eprintln!("Warning: {}", e),
+                    _ => {}
                 }
-                Err(e) => eprintln!("Warning: {}", e),
-                _ => {}
-            }
+                WalkState::Continue
+            })
+        });
+
+    // The walk is unordered, and symlinks or overlapping roots can reach a file twice
+    let mut files = files.into_inner().unwrap_or_else(|e| e.into_inner());
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Collect files matching glob `patterns`, along with files listed by `--files-from`.
+///
+/// Listed paths are taken literally, relative to `cwd` like the patterns.
+fn collect_input_files(
+    patterns: &[String],
+    listed_files: &[PathBuf],
+    cwd: &Path,
+    follow_symlinks: bool,
+    threads: usize,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let mut files = if patterns.is_empty() {
+        Vec::new()
+    } else {
+        collect_matching_files(patterns, cwd, follow_symlinks, threads)?
+    };
+    let mut seen: HashSet<PathBuf> = files.iter().cloned().collect();
+
+    let cwd = normalize_path(cwd)?;
+    for path in listed_files {
+        let absolute_path = cwd.join(path);
+        if !absolute_path.is_file() {
+            eprintln!("Warning: {}: No such file", path.display());
+            continue;
+        }
+        let normalized = normalize_path(&absolute_path)?;
+        if escapes_working_dir(&absolute_path, &normalized, &cwd) {
+            continue;
+        }
+        if seen.insert(normalized.clone()) {
+            files.push(normalized);
         }
     }
 
     Ok(files)
 }
 
-fn build_globset(patterns: &[String]) -> Result<Option<GlobSet>, Box<dyn std::error::Error>> {
+/// Read the paths listed in `source` (`-` for stdin), one per line or separated by NUL with `-0`.
+fn read_file_list(
+    source: &Path,
+    null_separated: bool,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let mut content = Vec::new();
+    if source == Path::new("-") {
+        std::io::stdin()
+            .read_to_end(&mut content)
+            .map_err(|e| format!("Failed to read file list from stdin: {}", e))?;
+    } else {
+        content = std::fs::read(source)
+            .map_err(|e| format!("Failed to read file list '{}': {}", source.display(), e))?;
+    }
+    Ok(parse_file_list(&content, null_separated))
+}
+
+fn parse_file_list(content: &[u8], null_separated: bool) -> Vec<PathBuf> {
+    let separator = if null_separated { b'\0' } else { b'\n' };
+    content
+        .split(|&b| b == separator)
+        .map(|entry| match entry {
+            [rest @ .., b'\r'] if !null_separated => rest,
+            entry => entry,
+        })
+        .filter(|entry| !entry.is_empty())
+        .map(bytes_to_path)
+        .collect()
+}
+
+/// File names are arbitrary bytes on Unix, e.g. in `git ls-files -z` output.
+fn bytes_to_path(bytes: &[u8]) -> PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+    }
+    #[cfg(not(unix))]
+    {
+        PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+    }
+}
+
+/// Never write through symlinks that point outside of the working directory,
+/// even if `--follow-symlinks` traverses them.
+fn escapes_working_dir(path: &Path, target: &Path, cwd: &Path) -> bool {
+    let escapes = write::escapes_root(path, target, cwd);
+    if escapes {
+        eprintln!(
+            "Warning: {}: Skipping symlink to '{}' outside of the working directory",
+            display_path(path),
+            target.display()
+        );
+    }
+    escapes
+}
+
+/// Build a walker over `roots` that honors ignore files hierarchically and skips default
+/// ignored directories on `threads` threads, like formatting. Symlink loops are reported as errors
+/// when following symlinks.
+fn build_walker(roots: &[PathBuf], follow_symlinks: bool, threads: usize) -> WalkBuilder {
+    let mut builder = WalkBuilder::new(roots.first().map_or(Path::new("."), PathBuf::as_path));
+    for root in roots.iter().skip(1) {
+        builder.add(root);
+    }
+    builder
+        .hidden(false)
+        .follow_links(follow_symlinks)
+        .threads(threads)
+        // `.gitignore` is honored outside of git repositories as well
+        .require_git(false);
+    for filename in IGNORE_FILENAMES {
+        builder.add_custom_ignore_filename(filename);
+    }
+    // The roots are always traversed, e.g. `oxk format 'oh_modules/**/*.ets'`.
+    // A root nested in another one is walked on its own rather than twice.
+    let roots = roots.to_vec();
+    builder.filter_entry(move |entry| {
+        if entry.depth() == 0 || !entry.file_type().is_some_and(|t| t.is_dir()) {
+            return true;
+        }
+        !roots.iter().any(|root| root == entry.path())
+            && !entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| DEFAULT_IGNORE_DIRS.contains(&name))
+    });
+    builder
+}
+
+/// `!pattern` removes the files it matches from the other patterns.
+fn is_negated(pattern: &str) -> bool {
+    pattern.starts_with('!')
+}
+
+/// Anchor a relative pattern of `--exclude` or `ignorePatterns` at `base`.
+fn anchor_pattern(base: &Path, pattern: &str) -> String {
+    if Path::new(pattern).is_absolute() {
+        return pattern.to_string();
+    }
+    let pattern = pattern.strip_prefix("./").unwrap_or(pattern);
+    format!("{}/{}", globset::escape(&base.to_string_lossy()), pattern)
+}
+
+/// Build a matcher of `patterns`, relative patterns are relative to `cwd`.
+fn build_globset(
+    patterns: &[String],
+    cwd: &Path,
+) -> Result<Option<GlobSet>, Box<dyn std::error::Error>> {
     if patterns.is_empty() {
         return Ok(None);
     }
 
     let mut builder = GlobSetBuilder::new();
     for pattern in patterns {
-        let absolute_pattern = to_absolute_pattern(pattern)?;
+        let absolute_pattern = to_absolute_pattern(pattern, cwd);
         let glob = Glob::new(&absolute_pattern)
             .map_err(|e| format!("Invalid glob pattern '{}': {}", pattern, e))?;
         builder.add(glob);
@@ -201,42 +620,37 @@ fn build_globset(patterns: &[String]) -> Result<Option<GlobSet>, Box<dyn std::er
     ))
 }
 
-fn to_absolute_pattern(pattern: &str) -> Result<String, Box<dyn std::error::Error>> {
+fn to_absolute_pattern(pattern: &str, cwd: &Path) -> String {
     let pattern_path = Path::new(pattern);
-    Ok(if pattern_path.is_absolute() {
+    if pattern_path.is_absolute() {
         pattern.to_string()
     } else {
-        env::current_dir()?
-            .join(pattern)
-            .to_string_lossy()
-            .into_owned()
-    })
+        cwd.join(pattern).to_string_lossy().into_owned()
+    }
 }
 
-fn determine_root(absolute_pattern: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
-    Ok(
-        if let Some(wildcard_pos) = absolute_pattern.find(['*', '?', '{', '[']) {
-            let prefix = Path::new(&absolute_pattern[..wildcard_pos]);
-            let mut current = prefix.to_path_buf();
-            while !current.exists() || !current.is_dir() {
-                if let Some(parent) = current.parent() {
-                    current = parent.to_path_buf();
-                } else {
-                    current = env::current_dir()?;
-                    break;
-                }
-            }
-            current
-        } else {
-            let path = Path::new(&absolute_pattern);
-            if path.is_file() {
-                return Ok(path.to_path_buf());
+fn determine_root(absolute_pattern: &str, cwd: &Path) -> PathBuf {
+    if let Some(wildcard_pos) = absolute_pattern.find(['*', '?', '{', '[']) {
+        let prefix = Path::new(&absolute_pattern[..wildcard_pos]);
+        let mut current = prefix.to_path_buf();
+        while !current.exists() || !current.is_dir() {
+            if let Some(parent) = current.parent() {
+                current = parent.to_path_buf();
+            } else {
+                current = cwd.to_path_buf();
+                break;
             }
-            path.parent()
-                .map(|p| p.to_path_buf())
-                .unwrap_or_else(|| env::current_dir().expect("Failed to get current directory"))
-        },
-    )
+        }
+        current
+    } else {
+        let path = Path::new(&absolute_pattern);
+        if path.is_file() {
+            return path.to_path_buf();
+        }
+        path.parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_else(|| cwd.to_path_buf())
+    }
 }
 
 fn normalize_path(path: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
@@ -252,37 +666,231 @@ fn normalize_path(path: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
         .map_err(|e| std::io::Error::other(format!("Failed to normalize path: {}", e)))?)
 }
 
-/// Format a single file as a tokio task
-async fn format_file_task(
-    path: PathBuf,
-    semaphore: Arc<Semaphore>,
-    format_args: crate::FormatArgs,
-) -> Result<(), String> {
-    // Acquire permit to limit concurrency
-    let _permit = semaphore
-        .acquire()
-        .await
-        .map_err(|e| format!("Semaphore error: {}", e))?;
+/// Format source read from stdin and print the result to stdout.
+///
+/// `filepath` only selects the format strategy, it does not need to exist.
+fn format_stdin(
+    filepath: &Path,
+    config_resolver: &ConfigResolver,
+    format_args: &crate::FormatArgs,
+) -> Result<i32, Box<dyn std::error::Error>> {
+    let mut bytes = Vec::new();
+    std::io::stdin()
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read from stdin: {}", e))?;
+
+    // Echo ignored files (e.g., lock files) unchanged so editors keep the buffer as is
+    if should_ignore_file(filepath) {
+        if !format_args.check && !format_args.diff {
+            write_stdout(&bytes)?;
+        }
+        return Ok(0);
+    }
 
+    let strategy = FormatFileStrategy::try_from(filepath.to_path_buf())
+        .map_err(|_| format!("Unsupported file type '{}'", filepath.display()))?;
+    let source_text = decode_source(bytes, format_args.encoding)
+        .map_err(|e| format!("{}: {}", filepath.display(), e))?;
+    let resolved_options = resolve_options(&strategy, config_resolver, &source_text)?;
+
+    let (formatted_code, is_changed) = match format_source(
+        &SourceFormatter::new(1),
+        &strategy,
+        &source_text,
+        resolved_options,
+    ) {
+        Ok(result) => result,
+        Err(diagnostics) => {
+            let report = FileError::Parse {
+                source_text,
+                diagnostics,
+            }
+            .into_report(filepath.to_path_buf());
+            print_file_report(
+                &report,
+                use_color(format_args, std::io::stderr().is_terminal()),
+            );
+            return Err(format!("Failed to format '{}'", filepath.display()).into());
+        }
+    };
+
+    if format_args.diff {
+        let colored = use_color(format_args, std::io::stdout().is_terminal());
+        print!(
+            "{}",
+            diff::unified_diff(
+                &filepath.display().to_string(),
+                &source_text,
+                &formatted_code,
+                colored
+            )
+        );
+    } else if !format_args.check {
+        let bytes = encode_source(&formatted_code, format_args.encoding)
+            .map_err(|e| format!("{}: {}", filepath.display(), e))?;
+        write_stdout(&bytes)?;
+    }
+
+    if (format_args.check || format_args.diff) && is_changed {
+        return Ok(EXIT_CODE_UNFORMATTED);
+    }
+    Ok(0)
+}
+
+/// Path of the formatted copy of `path` under `--out-dir`, mirroring its path relative to `root`.
+fn mirror_path(out_dir: &Path, root: &Path, path: &Path) -> Result<PathBuf, String> {
+    path.strip_prefix(root)
+        .map(|relative| out_dir.join(relative))
+        .map_err(|_| {
+            format!(
+                "'{}' is outside of the current directory and cannot be mirrored into '{}'",
+                path.display(),
+                out_dir.display()
+            )
+        })
+}
+
+/// Display a path relative to the current directory when possible.
+fn display_path(path: &Path) -> String {
+    env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| path.to_path_buf())
+        .display()
+        .to_string()
+}
+
+/// Print the results with the selected reporter and return the exit code of the summary.
+fn report_results(reports: &[FileReport], format_args: &crate::FormatArgs) -> i32 {
+    let check = format_args.check || format_args.diff;
+    let reporter = format_args.reporter;
+    let summary = Summary::from_reports(reports);
+
+    if reporter != Reporter::Default {
+        print!("{}", report::render(reporter, reports, check));
+        return summary.exit_code(check);
+    }
+
+    // The diff already names every changed file
+    if check && !format_args.diff && summary.changed > 0 {
+        let mut unformatted_files: Vec<_> = reports
+            .iter()
+            .filter(|report| report.status == FileStatus::Changed)
+            .map(|report| &report.path)
+            .collect();
+        unformatted_files.sort();
+        for path in &unformatted_files {
+            eprintln!("{}", display_path(path));
+        }
+        eprintln!(
+            "Format issues found in {} file(s). Run `oxk format` without `--check` or `--diff` to fix.",
+            unformatted_files.len()
+        );
+    }
+    if summary.failed > 0 && format_args.fail_fast {
+        eprintln!("Stopped after the first file that failed to format (--fail-fast).");
+    }
+    eprintln!("{}", summary.render(check));
+
+    summary.exit_code(check)
+}
+
+/// Whether the CLI can format files of this strategy, i.e. without an external formatter.
+fn is_supported_strategy(strategy: &FormatFileStrategy) -> bool {
+    !matches!(
+        strategy,
+        FormatFileStrategy::ExternalFormatter { .. }
+            | FormatFileStrategy::ExternalFormatterPackageJson { .. }
+    )
+}
+
+/// Whether to colorize output written to a stream, honoring `--color`, `--no-color` and `NO_COLOR`.
+fn use_color(format_args: &crate::FormatArgs, is_terminal: bool) -> bool {
+    format_args
+        .color
+        .unwrap_or_else(|| is_terminal && env::var_os("NO_COLOR").is_none())
+}
+
+/// Print a warning for files that are unsupported, and code frames for files that failed to parse.
+fn print_file_report(report: &FileReport, color: bool) {
+    match report.status {
+        FileStatus::Unsupported => {
+            eprintln!(
+                "Warning: {}: Unsupported file type",
+                display_path(&report.path)
+            );
+        }
+        FileStatus::Failed if report.source_text.is_some() => {
+            eprint!("{}", report::render_code_frames(report, color));
+        }
+        FileStatus::Failed => {
+            for diagnostic in &report.diagnostics {
+                eprintln!("{}", render_error(&report.path, diagnostic, color));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Render a failure that has no source text to show a code frame for, such as an I/O error.
+fn render_error(path: &Path, diagnostic: &OxcDiagnostic, color: bool) -> String {
+    // Scoped, its methods clash with `WalkBuilder::hidden`
+    use owo_colors::OwoColorize;
+
+    let label = if color {
+        "Error:".red().bold().to_string()
+    } else {
+        "Error:".to_string()
+    };
+    format!("{label} {}: {diagnostic}", display_path(path))
+}
+
+/// Write `content` to the mirror path of `path` in `--out-dir`, if it is set.
+///
+/// Files that are skipped or unsupported are copied verbatim, so the output directory is complete.
+async fn copy_to_out_dir(
+    format_args: &crate::FormatArgs,
+    path: &Path,
+    content: Vec<u8>,
+) -> Result<(), FileError> {
+    let Some(out_dir) = &format_args.out_dir else {
+        return Ok(());
+    };
+    let cwd = env::current_dir().map_err(|e| format!("Failed to get current directory: {}", e))?;
+    let out_path = mirror_path(out_dir, &cwd, path)?;
+    tokio::task::spawn_blocking(move || {
+        if let Some(parent) = out_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        write::write_atomic(&out_path, &content)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+    .map_err(|e| format!("Failed to write to '{}': {}", out_dir.display(), e))?;
+    Ok(())
+}
+
+/// Format a single file as a tokio task
+async fn format_file_task(path: PathBuf, context: Arc<FormatContext>) -> FileReport {
     // Use async file I/O for better performance in concurrent scenarios
-    format_file_async(&path, format_args)
-        .await
-        .map_err(|err| format!("{}: {err}", path.display()))
+    match format_file_async(&path, &context).await {
+        Ok(status) => FileReport::new(path, status),
+        Err(err) => err.into_report(path),
+    }
 }
 
 /// Format a single file using async I/O
 async fn format_file_async(
     path: &Path,
-    format_args: crate::FormatArgs,
-) -> Result<(), Box<dyn std::error::Error>> {
+    context: &Arc<FormatContext>,
+) -> Result<FileStatus, FileError> {
+    let format_args = &context.format_args;
+
     // Verify file exists
     let actual_path = if tokio::fs::metadata(path).await.is_ok() {
         path.to_path_buf()
     } else {
-        return Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            format!("File '{}' does not exist", path.display()),
-        )));
+        return Err(format!("File '{}' does not exist", path.display()).into());
     };
 
     // Read the file using async I/O
@@ -290,229 +898,357 @@ async fn format_file_async(
         .await
         .map_err(|e| format!("Failed to read file '{}': {}", actual_path.display(), e))?;
 
-    let source_text = String::from_utf8_lossy(&bytes).into_owned();
-
     // Skip empty files silently
-    if source_text.is_empty() {
-        return Ok(());
+    if bytes.is_empty() {
+        copy_to_out_dir(format_args, &actual_path, bytes).await?;
+        return Ok(FileStatus::Skipped);
     }
 
     // Skip ignored files silently (e.g., lock files, ignored JSON files)
     if should_ignore_file(&actual_path) {
-        return Ok(());
+        copy_to_out_dir(format_args, &actual_path, bytes).await?;
+        return Ok(FileStatus::Skipped);
     }
 
-    // Determine format strategy from file path
-    let strategy = FormatFileStrategy::try_from(actual_path.clone())
-        .map_err(|_| format!("Unsupported file type '{}'", actual_path.display()))?;
+    // Determine format strategy from file path.
+    // Files that need an external formatter (e.g. Prettier) are not supported by the CLI
+    let strategy = match FormatFileStrategy::try_from(actual_path.clone()) {
+        Ok(strategy) if is_supported_strategy(&strategy) => strategy,
+        _ => {
+            copy_to_out_dir(format_args, &actual_path, bytes).await?;
+            return Ok(FileStatus::Unsupported);
+        }
+    };
 
-    // Build config from command line arguments
-    // For TOML and JSON files, we need to use ConfigResolver
-    // For JS/TS files, we can build FormatOptions directly
-    // For ExternalFormatter files (like yaml, markdown), we need to check if napi feature is available
-    let resolved_options = match &strategy {
-        FormatFileStrategy::OxfmtToml { .. } | FormatFileStrategy::OxfmtJson { .. } => {
-            // Build JSON config from command line arguments
-            let mut config_value = Value::Object(serde_json::Map::new());
-            config_value["trailingCommas"] = Value::String("none".to_string());
+    // Never write back a lossy decoding, e.g. of a GBK file without `--encoding gbk`
+    let source_text = decode_source(bytes, format_args.encoding)?;
+    let resolved_options = resolve_options(&strategy, &context.config_resolver, &source_text)?;
+
+    // Skip parsing entirely if the same content was already formatted with the same options
+    let cache = context
+        .cache
+        .as_ref()
+        .map(|cache| (cache, format!("{:?}", resolved_options)));
+    if let Some((cache, fingerprint)) = &cache
+        && cache.is_formatted(
+            &actual_path,
+            FormatCache::key(source_text.as_bytes(), fingerprint),
+        )
+    {
+        return Ok(FileStatus::Unchanged);
+    }
 
-            if let Some(v) = format_args.indent_style {
-                config_value["indentStyle"] = Value::String(format!("{:?}", v).to_lowercase());
-            }
-            if let Some(v) = format_args.indent_width {
-                config_value["indentWidth"] = Value::Number(v.value().into());
-            }
-            if let Some(v) = format_args.line_ending {
-                config_value["lineEnding"] = Value::String(format!("{:?}", v).to_lowercase());
-            }
-            if let Some(v) = format_args.line_width {
-                config_value["lineWidth"] = Value::Number(v.value().into());
-            }
-            if let Some(v) = format_args.quote_style {
-                config_value["quoteStyle"] = Value::String(format!("{:?}", v).to_lowercase());
-            }
-            if let Some(v) = format_args.jsx_quote_style {
-                config_value["jsxQuoteStyle"] = Value::String(format!("{:?}", v).to_lowercase());
-            }
-            if let Some(v) = format_args.trailing_commas {
-                config_value["trailingCommas"] = Value::String(format!("{:?}", v).to_lowercase());
-            }
-            if let Some(v) = format_args.semicolons {
-                config_value["semicolons"] = Value::String(format!("{:?}", v).to_lowercase());
-            }
-            if let Some(v) = format_args.arrow_parentheses {
-                config_value["arrowParentheses"] = Value::String(format!("{:?}", v).to_lowercase());
-            }
-            if let Some(v) = format_args.bracket_spacing {
-                config_value["bracketSpacing"] = Value::Bool(v.value());
-            }
-            if let Some(v) = format_args.bracket_same_line {
-                config_value["bracketSameLine"] = Value::Bool(v.value());
-            }
-            if let Some(v) = format_args.attribute_position {
-                config_value["attributePosition"] =
-                    Value::String(format!("{:?}", v).to_lowercase());
-            }
-            if let Some(v) = format_args.expand {
-                config_value["expand"] = Value::String(format!("{}", v).to_lowercase());
-            }
-            if let Some(v) = format_args.experimental_operator_position {
-                config_value["experimentalOperatorPosition"] =
-                    Value::String(format!("{}", v).to_lowercase());
-            }
-            if let Some(v) = format_args.experimental_ternaries {
-                config_value["experimentalTernaries"] = Value::Bool(v);
-            }
-            if let Some(v) = format_args.embedded_language_formatting {
-                config_value["embeddedLanguageFormatting"] =
-                    Value::String(format!("{:?}", v).to_lowercase());
-            }
+    // Run CPU-intensive parsing and formatting in a blocking task, at most `--thread` at a time
+    let permit = context
+        .cpu_permits
+        .acquire()
+        .await
+        .map_err(|e| format!("Semaphore error: {}", e))?;
+    let blocking_context = context.clone();
+    let (source_text, result) = tokio::task::spawn_blocking(move || {
+        let result = format_source(
+            &blocking_context.formatter,
+            &strategy,
+            &source_text,
+            resolved_options,
+        );
+        (source_text, result)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?;
+    drop(permit);
+    let (formatted_code, is_changed) = match result {
+        Ok(result) => result,
+        Err(diagnostics) => {
+            return Err(FileError::Parse {
+                source_text,
+                diagnostics,
+            });
+        }
+    };
 
-            // Use ConfigResolver to resolve options for TOML/JSON files
-            let mut config_resolver = ConfigResolver::from_value(config_value);
-            if let Err(err) = config_resolver.build_and_validate() {
-                return Err(format!("Failed to parse configuration: {}", err).into());
-            }
-            let mut resolved_options = config_resolver.resolve(&strategy);
+    let status = if is_changed {
+        FileStatus::Changed
+    } else {
+        FileStatus::Unchanged
+    };
 
-            // Fix quote_properties: Oxfmtrc's deserialization may not properly handle quoteProperties,
-            // so we manually override it to Consistent for JSON/JSON5/JSONC files
-            if let ResolvedOptions::OxfmtJson { json_options, .. } = &mut resolved_options {
-                json_options.quote_properties = json5format::QuoteProperties::Always;
-            }
+    // Print the changes that would be applied
+    if format_args.diff && is_changed {
+        let colored = use_color(format_args, std::io::stdout().is_terminal());
+        print!(
+            "{}",
+            diff::unified_diff(
+                &display_path(&actual_path),
+                &source_text,
+                &formatted_code,
+                colored
+            )
+        );
+    }
 
-            resolved_options
+    // Formatted copies are written whether they changed or not, the source is left untouched
+    if format_args.out_dir.is_some() {
+        let content = encode_source(&formatted_code, format_args.encoding)?.into_owned();
+        copy_to_out_dir(format_args, &actual_path, content).await?;
+        return Ok(status);
+    }
+
+    // In check or diff mode, only report the status and never touch the file.
+    // Unchanged files are not written either, so their mtime is kept for incremental builds
+    if format_args.check || format_args.diff || !is_changed {
+        if let Some((cache, fingerprint)) = &cache
+            && !is_changed
+        {
+            cache.insert(
+                &actual_path,
+                FormatCache::key(source_text.as_bytes(), fingerprint),
+            );
         }
-        FormatFileStrategy::OxcFormatter { .. } => {
-            // For JS/TS files, build FormatOptions directly
-            let mut option = FormatOptions {
-                quote_properties: oxc_formatter::QuoteProperties::Consistent,
-                ..Default::default()
-            };
+        return Ok(status);
+    }
 
-            if let Some(v) = format_args.indent_style {
-                option.indent_style = v;
-            }
-            if let Some(v) = format_args.indent_width {
-                option.indent_width = v;
-            }
-            if let Some(v) = format_args.line_ending {
-                option.line_ending = v;
-            }
-            if let Some(v) = format_args.line_width {
-                option.line_width = v;
-            }
-            if let Some(v) = format_args.quote_style {
-                option.quote_style = v;
-            }
-            if let Some(v) = format_args.jsx_quote_style {
-                option.jsx_quote_style = v;
-            }
-            if let Some(v) = format_args.trailing_commas {
-                option.trailing_commas = v;
-            }
-            if let Some(v) = format_args.semicolons {
-                option.semicolons = v;
-            }
-            if let Some(v) = format_args.arrow_parentheses {
-                option.arrow_parentheses = v;
-            }
-            if let Some(v) = format_args.bracket_spacing {
-                option.bracket_spacing = v;
-            }
-            if let Some(v) = format_args.bracket_same_line {
-                option.bracket_same_line = v;
-            }
-            if let Some(v) = format_args.attribute_position {
-                option.attribute_position = v;
-            }
-            if let Some(v) = format_args.expand {
-                option.expand = v;
-            }
-            if let Some(v) = format_args.experimental_operator_position {
-                option.experimental_operator_position = v;
-            }
-            if let Some(v) = format_args.experimental_ternaries {
-                option.experimental_ternaries = v;
-            }
-            if let Some(v) = format_args.embedded_language_formatting {
-                option.embedded_language_formatting = v;
-            }
+    // Write back to the actual path, paths are canonical so symlinks are never replaced
+    let content = encode_source(&formatted_code, format_args.encoding)?.into_owned();
+    let write_path = actual_path.clone();
+    tokio::task::spawn_blocking(move || write::write_atomic(&write_path, &content))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+        .map_err(|e| format!("Failed to write to '{}': {}", actual_path.display(), e))?;
 
-            ResolvedOptions::OxcFormatter {
-                format_options: option,
-                external_options: Value::Object(serde_json::Map::new()),
-                insert_final_newline: true,
+    if let Some((cache, fingerprint)) = &cache {
+        cache.insert(
+            &actual_path,
+            FormatCache::key(formatted_code.as_bytes(), fingerprint),
+        );
+    }
+
+    Ok(status)
+}
+
+/// Load the config file (`--config` or the nearest `.oxfmtrc.json(c)` from `cwd`)
+/// and layer command line flags on top of it.
+///
+/// Returns the resolver along with the exclude patterns of `--exclude` and `ignorePatterns`.
+/// They are relative to the directory of the config file, or to `cwd` without one,
+/// so running from a subdirectory excludes the same files.
+fn load_config(
+    cwd: &Path,
+    format_args: &crate::FormatArgs,
+) -> Result<(ConfigResolver, Vec<String>), Box<dyn std::error::Error>> {
+    let oxfmtrc_path = resolve_oxfmtrc_path(cwd, format_args.config.as_deref());
+    let mut config_resolver =
+        ConfigResolver::from_config_paths(cwd, oxfmtrc_path.as_deref(), None)?;
+    let ignore_patterns = config_resolver
+        .build_and_validate()
+        .map_err(|err| format!("Failed to parse configuration: {}", err))?;
+
+    let base = config_dir(cwd, format_args);
+    let excludes = format_args
+        .excludes
+        .iter()
+        .chain(&ignore_patterns)
+        .map(|pattern| anchor_pattern(&base, pattern))
+        .collect();
+
+    // `--line-ending auto` detects the line ending of each file, a fixed one is an override below
+    if let Some(end_of_line) = format_args.line_ending {
+        config_resolver.set_auto_line_ending(end_of_line == EndOfLine::Auto);
+    }
+
+    // Quote properties consistently by default, unless the config file says otherwise
+    let has_quote_properties = config_resolver.raw_config().get("quoteProps").is_some();
+    let format_args = format_args.clone();
+    config_resolver.override_format_options(move |options, _strategy| {
+        if !has_quote_properties {
+            options.quote_properties = oxc_formatter::QuoteProperties::Consistent;
+        }
+        apply_cli_overrides(&format_args, options);
+    });
+
+    Ok((config_resolver, excludes))
+}
+
+/// Directory that relative patterns are anchored at: the directory of the config file,
+/// or `cwd` without one.
+fn config_dir(cwd: &Path, format_args: &crate::FormatArgs) -> PathBuf {
+    resolve_oxfmtrc_path(cwd, format_args.config.as_deref())
+        .as_deref()
+        .and_then(Path::parent)
+        .unwrap_or(cwd)
+        .to_path_buf()
+}
+
+/// Anchor relative file patterns at the config directory like the excludes,
+/// so running from a subdirectory formats the same files.
+fn anchor_file_patterns(cwd: &Path, format_args: &crate::FormatArgs) -> Vec<String> {
+    let base = config_dir(cwd, format_args);
+    format_args
+        .file
+        .iter()
+        .map(|pattern| match pattern.strip_prefix('!') {
+            Some(negated) => format!("!{}", anchor_pattern(&base, negated)),
+            None => anchor_pattern(&base, pattern),
+        })
+        .collect()
+}
+
+/// Apply explicit command line flags, which take precedence over the config file.
+fn apply_cli_overrides(format_args: &crate::FormatArgs, options: &mut FormatOptions) {
+    if let Some(v) = format_args.indent_style {
+        options.indent_style = v;
+    }
+    if let Some(v) = format_args.indent_width {
+        options.indent_width = v;
+    }
+    if let Some(v) = format_args.line_ending.and_then(EndOfLine::line_ending) {
+        options.line_ending = v;
+    }
+    if let Some(v) = format_args.line_width {
+        options.line_width = v;
+    }
+    if let Some(v) = format_args.quote_style {
+        options.quote_style = v;
+    }
+    if let Some(v) = format_args.jsx_quote_style {
+        options.jsx_quote_style = v;
+    }
+    if let Some(v) = format_args.trailing_commas {
+        options.trailing_commas = v;
+    }
+    if let Some(v) = format_args.semicolons {
+        options.semicolons = v;
+    }
+    if let Some(v) = format_args.arrow_parentheses {
+        options.arrow_parentheses = v;
+    }
+    if let Some(v) = format_args.bracket_spacing {
+        options.bracket_spacing = v;
+    }
+    if let Some(v) = format_args.bracket_same_line {
+        options.bracket_same_line = v;
+    }
+    if let Some(v) = format_args.attribute_position {
+        options.attribute_position = v;
+    }
+    if let Some(v) = format_args.expand {
+        options.expand = v;
+    }
+    if let Some(v) = format_args.experimental_operator_position {
+        options.experimental_operator_position = v;
+    }
+    if let Some(v) = format_args.experimental_ternaries {
+        options.experimental_ternaries = v;
+    }
+    if let Some(v) = format_args.embedded_language_formatting {
+        options.embedded_language_formatting = v;
+    }
+}
+
+/// Resolve format options for a file, the source decides the line ending with `endOfLine: "auto"`.
+fn resolve_options(
+    strategy: &FormatFileStrategy,
+    config_resolver: &ConfigResolver,
+    source_text: &str,
+) -> Result<ResolvedOptions, Box<dyn std::error::Error>> {
+    match strategy {
+        FormatFileStrategy::OxcFormatter { .. } | FormatFileStrategy::OxfmtToml { .. } => {
+            Ok(config_resolver.resolve_with_source(strategy, source_text))
+        }
+        FormatFileStrategy::OxfmtJson { .. } => {
+            let mut resolved_options = config_resolver.resolve_with_source(strategy, source_text);
+
+            // Fix quote_properties: Oxfmtrc's deserialization may not properly handle quoteProperties,
+            // so we manually override it to Always for JSON/JSON5/JSONC files
+            if let ResolvedOptions::OxfmtJson { json_options, .. } = &mut resolved_options {
+                json_options.quote_properties = json5format::QuoteProperties::Always;
             }
+
+            Ok(resolved_options)
         }
         FormatFileStrategy::ExternalFormatter { parser_name, .. }
         | FormatFileStrategy::ExternalFormatterPackageJson { parser_name, .. } => {
             // ExternalFormatter files (like yaml, markdown) require napi feature for formatting
             // oxk CLI doesn't have napi feature, so we give a clear error message
-            return Err(format!(
+            Err(format!(
                 "File type '{}' (parser: {}) requires external formatter support (e.g., Prettier). \
                 oxk CLI only supports JavaScript/TypeScript, TOML, and JSON/JSON5/JSONC files. \
                 For other file types, please use npm/oxk with external formatter callbacks or use a different formatter.",
-                actual_path.display(),
+                strategy.path().display(),
                 parser_name
-            ).into());
+            ).into())
         }
-    };
+    }
+}
 
-    // Run CPU-intensive parsing and formatting in a blocking task
-    let actual_path_clone = actual_path.clone();
-    let strategy_clone = match &strategy {
-        FormatFileStrategy::OxcFormatter { path, source_type } => {
-            FormatFileStrategy::OxcFormatter {
-                path: path.clone(),
-                source_type: *source_type,
-            }
-        }
-        FormatFileStrategy::OxfmtToml { path } => {
-            FormatFileStrategy::OxfmtToml { path: path.clone() }
-        }
-        FormatFileStrategy::OxfmtJson { path, json_type } => FormatFileStrategy::OxfmtJson {
-            path: path.clone(),
-            json_type: *json_type,
-        },
-        FormatFileStrategy::ExternalFormatter { .. }
-        | FormatFileStrategy::ExternalFormatterPackageJson { .. } => {
-            // This should never happen as we check earlier in resolved_options match
-            unreachable!("ExternalFormatter should be rejected earlier")
+/// Format source text with a shared `SourceFormatter`.
+///
+/// Returns the formatted code and whether it differs from the source.
+/// Parse errors are returned as diagnostics pointing into the source without its byte-order mark.
+fn format_source(
+    formatter: &SourceFormatter,
+    strategy: &FormatFileStrategy,
+    source_text: &str,
+    resolved_options: ResolvedOptions,
+) -> Result<(String, bool), Vec<OxcDiagnostic>> {
+    // Formatters do not expect a byte-order mark, it is kept as is on the output
+    let (bom, source_text) = split_bom(source_text);
+
+    // Format the file
+    match formatter.format(strategy, source_text, resolved_options) {
+        format::FormatResult::Success { code, is_changed } => {
+            Ok((format!("{bom}{code}"), is_changed))
         }
-    };
-    let formatted_code = tokio::task::spawn_blocking(move || {
-        // Create formatter
-        let formatter = SourceFormatter::new(1);
+        format::FormatResult::Error(diagnostics) => Err(diagnostics),
+    }
+}
 
-        // Format the file
-        match formatter.format(&strategy_clone, &source_text, resolved_options) {
-            format::FormatResult::Success { code, .. } => {
-                // Check for parse errors by comparing with original
-                // If there were parse errors, the formatter would have returned an error
-                Ok(code)
-            }
-            format::FormatResult::Error(diagnostics) => {
-                // Format parse/format errors
-                let mut error_msg = format!(
-                    "AST_PARSE_ERROR: Parser errors in '{}':\n",
-                    actual_path_clone.display()
-                );
-                for diagnostic in diagnostics {
-                    error_msg.push_str(&format!("{diagnostic:?}\n"));
-                }
-                Err(error_msg)
+/// Decode file content as strict UTF-8, or in the encoding given by `--encoding`.
+fn decode_source(bytes: Vec<u8>, encoding: Option<&'static Encoding>) -> Result<String, String> {
+    match encoding {
+        Some(encoding) if encoding != encoding_rs::UTF_8 => encoding
+            .decode_without_bom_handling_and_without_replacement(&bytes)
+            .map(Cow::into_owned)
+            .ok_or_else(|| format!("File is not valid {}", encoding.name())),
+        _ => decode_utf8(bytes).map_err(|_| {
+            "File is not valid UTF-8, use `--encoding` to format files in other encodings"
+                .to_string()
+        }),
+    }
+}
+
+/// Encode formatted code for writing, in the encoding given by `--encoding`.
+fn encode_source<'a>(
+    code: &'a str,
+    encoding: Option<&'static Encoding>,
+) -> Result<Cow<'a, [u8]>, String> {
+    match encoding {
+        Some(encoding) if encoding != encoding_rs::UTF_8 => {
+            let (bytes, _, has_unmappable) = encoding.encode(code);
+            if has_unmappable {
+                return Err(format!(
+                    "Formatted code contains characters that cannot be written as {}",
+                    encoding.name()
+                ));
             }
+            Ok(bytes)
         }
-    })
-    .await
-    .map_err(|e| format!("Task join error: {}", e))?
-    .map_err(|e| Box::new(std::io::Error::other(e)) as Box<dyn std::error::Error>)?;
+        _ => Ok(Cow::Borrowed(code.as_bytes())),
+    }
+}
 
-    // Write back to the actual path using async I/O
-    tokio::fs::write(&actual_path, formatted_code)
-        .await
-        .map_err(|_| format!("Failed to write to '{}'", actual_path.display()).into())
+fn write_stdout(bytes: &[u8]) -> Result<(), String> {
+    std::io::stdout()
+        .write_all(bytes)
+        .map_err(|e| format!("Failed to write to stdout: {}", e))
+}
+
+/// Split the UTF-8 byte-order mark, if any, from the source.
+fn split_bom(source_text: &str) -> (&'static str, &str) {
+    match source_text.strip_prefix(BOM) {
+        Some(rest) => (BOM, rest),
+        None => ("", source_text),
+    }
 }
 
 #[cfg(test)]
@@ -520,7 +1256,11 @@ mod tests {
     use format::{FormatFileStrategy, ResolvedOptions, SourceFormatter};
     use oxc_formatter::FormatOptions;
     use serde_json::Value;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+
+    use super::test_utils::TempDir;
+    use super::{FileStatus, FormatContext};
 
     fn format_code(path: &str, source: &str) -> Result<String, String> {
         let strategy = FormatFileStrategy::try_from(PathBuf::from(path))
@@ -617,6 +1357,560 @@ struct Index {
         );
     }
 
+    #[test]
+    fn test_format_files_with_shared_formatter() {
+        let dir = TempDir::new("pool");
+        // More files than are in flight at once
+        let files: Vec<_> = (0..10)
+            .map(|i| {
+                let path = dir.path().join(format!("module{i}.json5"));
+                std::fs::write(&path, "{a:   1}\n").expect("Failed to write temp file");
+                path
+            })
+            .collect();
+
+        let args = crate::FormatArgs {
+            thread: 2,
+            ..Default::default()
+        };
+        let (config_resolver, _) =
+            super::load_config(dir.path(), &args).expect("Config should load");
+        let context = Arc::new(FormatContext::new(config_resolver, None, args));
+        let runtime = tokio::runtime::Runtime::new().expect("Failed to create runtime");
+        let reports = runtime
+            .block_on(super::format_files(context, files.clone()))
+            .expect("Formatting should succeed");
+
+        assert_eq!(reports.len(), files.len());
+        for path in &files {
+            assert_eq!(
+                std::fs::read_to_string(path).expect("Failed to read temp file"),
+                "{ \"a\": 1 }\n"
+            );
+        }
+    }
+
+    #[test]
+    fn test_keeps_bom_and_detected_line_ending() {
+        let dir = TempDir::new("bom");
+        std::fs::write(
+            dir.path().join(".oxfmtrc.json"),
+            r#"{ "endOfLine": "auto" }"#,
+        )
+        .expect("Failed to write temp file");
+        let path = dir.path().join("module.json5");
+        std::fs::write(&path, "\u{feff}{a:   1}\r\n").expect("Failed to write temp file");
+
+        let args = crate::FormatArgs::default();
+        let (config_resolver, _) =
+            super::load_config(dir.path(), &args).expect("Config should load");
+        let context = Arc::new(FormatContext::new(config_resolver, None, args));
+        let runtime = tokio::runtime::Runtime::new().expect("Failed to create runtime");
+        runtime
+            .block_on(super::format_file_async(&path, &context))
+            .expect("Formatting should succeed");
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("module.json5"))
+                .expect("Failed to read temp file"),
+            "\u{feff}{ \"a\": 1 }\r\n"
+        );
+    }
+
+    #[test]
+    fn test_non_utf8_source_is_not_written() {
+        let dir = TempDir::new("encoding");
+        // "café" in Latin-1
+        let source = b"{a:   'caf\xe9'}\n";
+        let path = dir.path().join("module.json5");
+        std::fs::write(&path, source).expect("Failed to write temp file");
+
+        let runtime = tokio::runtime::Runtime::new().expect("Failed to create runtime");
+        let format = |args: crate::FormatArgs| {
+            let (config_resolver, _) =
+                super::load_config(dir.path(), &args).expect("Config should load");
+            let context = Arc::new(FormatContext::new(config_resolver, None, args));
+            runtime.block_on(super::format_file_async(&path, &context))
+        };
+
+        let err = format(crate::FormatArgs::default()).expect_err("Invalid UTF-8 should fail");
+        assert!(err.to_string().contains("not valid UTF-8"));
+        assert_eq!(std::fs::read(&path).expect("Failed to read"), source);
+
+        let args = crate::FormatArgs {
+            encoding: encoding_rs::Encoding::for_label(b"latin1"),
+            ..Default::default()
+        };
+        assert_eq!(
+            format(args).expect("Latin-1 should be decoded"),
+            FileStatus::Changed
+        );
+        assert_eq!(
+            std::fs::read(&path).expect("Failed to read"),
+            b"{ \"a\": 'caf\xe9' }\n"
+        );
+    }
+
+    #[test]
+    fn test_listed_files_are_literal() {
+        let dir = TempDir::new("files-from");
+        let literal = dir.path().join("Index[1].ets");
+        std::fs::write(&literal, "let a = 1\n").expect("Failed to write temp file");
+        std::fs::write(dir.path().join("Index1.ets"), "let a = 1\n")
+            .expect("Failed to write temp file");
+
+        let list = format!("{}\0missing.ets\0", literal.display());
+        let listed = super::parse_file_list(list.as_bytes(), true);
+        assert_eq!(listed, [literal.clone(), PathBuf::from("missing.ets")]);
+        assert_eq!(
+            super::parse_file_list(b"a.ets\r\n\nb.ets", false),
+            [PathBuf::from("a.ets"), PathBuf::from("b.ets")]
+        );
+
+        // `Index[1].ets` would match `Index1.ets` as a glob
+        let files = super::collect_input_files(&[], &listed, dir.path(), false, 1)
+            .expect("Files should be collected");
+        assert_eq!(
+            files,
+            [super::normalize_path(&literal).expect("Normalized")]
+        );
+    }
+
+    #[test]
+    fn test_render_error() {
+        let path = Path::new("/project/a.ets");
+        let message = "Failed to read file";
+        let diagnostic = oxc_diagnostics::OxcDiagnostic::error(message);
+        assert_eq!(
+            super::render_error(path, &diagnostic, false),
+            format!("Error: {}: {message}", super::display_path(path))
+        );
+        let colored = super::render_error(path, &diagnostic, true);
+        assert!(colored.starts_with("\u{1b}["));
+        assert!(colored.ends_with(&format!("{}: {message}", super::display_path(path))));
+    }
+
+    #[test]
+    fn test_watch_rejects_out_dir() {
+        let args = crate::FormatArgs {
+            file: vec!["src".to_string()],
+            watch: true,
+            out_dir: Some(PathBuf::from("dist")),
+            ..Default::default()
+        };
+        let err = super::format(args).expect_err("`--watch` with `--out-dir` should fail");
+        assert!(err.to_string().starts_with("`--watch` cannot be combined"));
+    }
+
+    #[test]
+    fn test_reporter_rejects_stdin_and_watch() {
+        let reporter_args = || crate::FormatArgs {
+            file: vec!["src".to_string()],
+            reporter: super::Reporter::Json,
+            ..Default::default()
+        };
+        let args = crate::FormatArgs {
+            stdin_filepath: Some(PathBuf::from("a.ts")),
+            ..reporter_args()
+        };
+        let err = super::format(args).expect_err("`--reporter` with stdin should fail");
+        assert!(
+            err.to_string()
+                .starts_with("`--reporter` cannot be combined")
+        );
+
+        let args = crate::FormatArgs {
+            watch: true,
+            ..reporter_args()
+        };
+        let err = super::format(args).expect_err("`--reporter` with `--watch` should fail");
+        assert!(
+            err.to_string()
+                .starts_with("`--reporter` cannot be combined")
+        );
+    }
+
+    #[test]
+    fn test_cache_rejects_watch_and_staged() {
+        let cache_args = || crate::FormatArgs {
+            file: vec!["src".to_string()],
+            cache: true,
+            ..Default::default()
+        };
+        for args in [
+            crate::FormatArgs {
+                watch: true,
+                ..cache_args()
+            },
+            crate::FormatArgs {
+                staged: true,
+                ..cache_args()
+            },
+        ] {
+            let err = super::format(args).expect_err("`--cache` should be rejected");
+            assert!(err.to_string().starts_with("`--cache` cannot be combined"));
+        }
+    }
+
+    #[test]
+    fn test_mirror_path() {
+        let out_dir = Path::new("/project/dist");
+        let root = Path::new("/project");
+        assert_eq!(
+            super::mirror_path(out_dir, root, Path::new("/project/src/pages/Index.ets")),
+            Ok(PathBuf::from("/project/dist/src/pages/Index.ets"))
+        );
+        assert!(super::mirror_path(out_dir, root, Path::new("/shared/Index.ets")).is_err());
+    }
+
+    #[test]
+    fn test_check_mode_does_not_write() {
+        let dir = TempDir::new("check");
+        let source = "const a = 1;   \n\n\n";
+        let path = dir.path().join("index.ts");
+        std::fs::write(&path, source).expect("Failed to write temp file");
+
+        let args = crate::FormatArgs {
+            check: true,
+            ..Default::default()
+        };
+        let (config_resolver, _) =
+            super::load_config(dir.path(), &args).expect("Config should load");
+        let context = Arc::new(FormatContext::new(config_resolver, None, args));
+        let runtime = tokio::runtime::Runtime::new().expect("Failed to create runtime");
+        let status = runtime
+            .block_on(super::format_file_async(&path, &context))
+            .expect("Check should succeed");
+
+        assert_eq!(status, FileStatus::Changed);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("index.ts")).expect("Failed to read temp file"),
+            source,
+            "Check mode must not write the file"
+        );
+    }
+
+    #[test]
+    fn test_collect_matching_files_honors_ignore_files() {
+        let dir = TempDir::new("ignore");
+        for file in [
+            "entry/src/Index.ets",
+            "entry/src/Mock.ets",
+            "entry/generated/Gen.ets",
+            "oh_modules/pkg/Index.ets",
+            "build/default/Index.ets",
+        ] {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().expect("Parent directory"))
+                .expect("Failed to create temp dir");
+            std::fs::write(path, "let a = 1\n").expect("Failed to write temp file");
+        }
+        std::fs::write(dir.path().join(".gitignore"), "generated/\n")
+            .expect("Failed to write temp file");
+        std::fs::write(dir.path().join("entry/.oxkignore"), "Mock.ets\n")
+            .expect("Failed to write temp file");
+
+        let root = dir
+            .path()
+            .canonicalize()
+            .expect("Failed to canonicalize temp dir");
+        let collect = |patterns: &[String]| {
+            super::collect_matching_files(patterns, dir.path(), false, 2)
+                .expect("Files should be collected")
+                .into_iter()
+                .map(|path| {
+                    path.strip_prefix(&root)
+                        .expect("File should be under root")
+                        .to_path_buf()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let pattern = format!("{}/**/*.ets", dir.path().display());
+        assert_eq!(
+            collect(std::slice::from_ref(&pattern)),
+            [Path::new("entry/src/Index.ets")]
+        );
+
+        // A pattern starting inside a default ignored directory is walked along with the others
+        let nested = format!("{}/oh_modules/**/*.ets", dir.path().display());
+        assert_eq!(
+            collect(&[pattern, nested]),
+            [
+                Path::new("entry/src/Index.ets"),
+                Path::new("oh_modules/pkg/Index.ets")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_negated_patterns() {
+        let dir = TempDir::new("negated");
+        for file in ["pages/Index.ets", "pages/Index.test.ets"] {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().expect("Parent directory"))
+                .expect("Failed to create temp dir");
+            std::fs::write(path, "let a = 1\n").expect("Failed to write temp file");
+        }
+
+        let patterns = [
+            format!("{}/**/*.ets", dir.path().display()),
+            format!("!{}/**/*.test.ets", dir.path().display()),
+        ];
+        let files = super::collect_matching_files(&patterns, dir.path(), false, 1)
+            .expect("Files should be collected");
+        let root = dir
+            .path()
+            .canonicalize()
+            .expect("Failed to canonicalize temp dir");
+        assert_eq!(files, [root.join("pages/Index.ets")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_symlinks() {
+        let dir = TempDir::new("symlinks");
+        for sub in ["entry", "shared"] {
+            std::fs::create_dir_all(dir.path().join(sub)).expect("Failed to create temp dir");
+        }
+        std::fs::write(dir.path().join("shared/Util.ets"), "let a = 1\n")
+            .expect("Failed to write temp file");
+        std::os::unix::fs::symlink(dir.path().join("shared"), dir.path().join("entry/shared"))
+            .expect("Failed to create symlink");
+        // A loop back to an ancestor must not be walked forever
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("shared/loop"))
+            .expect("Failed to create symlink");
+        // Files outside of the working directory are never written, even when traversed
+        let outside = TempDir::new("symlinks-outside");
+        std::fs::write(outside.path().join("Outside.ets"), "let a = 1\n")
+            .expect("Failed to write temp file");
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("entry/outside"))
+            .expect("Failed to create symlink");
+
+        let patterns = [format!("{}/entry/**/*.ets", dir.path().display())];
+        let files = super::collect_matching_files(&patterns, dir.path(), false, 1)
+            .expect("Files should be collected");
+        assert!(files.is_empty());
+
+        let files = super::collect_matching_files(&patterns, dir.path(), true, 1)
+            .expect("Files should be collected");
+        let root = dir
+            .path()
+            .canonicalize()
+            .expect("Failed to canonicalize temp dir");
+        assert_eq!(files, [root.join("shared/Util.ets")]);
+    }
+
+    #[test]
+    fn test_excludes_are_relative_to_config_file() {
+        let dir = TempDir::new("excludes");
+        std::fs::create_dir_all(dir.path().join("entry/src")).expect("Failed to create temp dir");
+        std::fs::write(
+            dir.path().join(".oxfmtrc.json"),
+            r#"{ "ignorePatterns": ["entry/generated/**"] }"#,
+        )
+        .expect("Failed to write temp file");
+
+        let args = crate::FormatArgs {
+            excludes: vec!["./entry/mock/**".to_string()],
+            ..Default::default()
+        };
+        // The same excludes are used from the project root and from a subdirectory
+        for cwd in [dir.path().to_path_buf(), dir.path().join("entry/src")] {
+            let (_, excludes) = super::load_config(&cwd, &args).expect("Config should load");
+            assert_eq!(
+                excludes,
+                [
+                    format!("{}/entry/mock/**", dir.path().display()),
+                    format!("{}/entry/generated/**", dir.path().display()),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_patterns_are_relative_to_config_file() {
+        let dir = TempDir::new("patterns");
+        for file in [
+            "src/Index.ets",
+            "entry/src/Index.ets",
+            "entry/src/generated/Gen.ets",
+        ] {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().expect("Parent directory"))
+                .expect("Failed to create temp dir");
+            std::fs::write(path, "let a = 1\n").expect("Failed to write temp file");
+        }
+        std::fs::write(
+            dir.path().join(".oxfmtrc.json"),
+            r#"{ "ignorePatterns": ["entry/src/generated/**"] }"#,
+        )
+        .expect("Failed to write temp file");
+
+        let args = crate::FormatArgs {
+            file: vec!["entry/**/*.ets".to_string(), "!**/Gen.ets".to_string()],
+            ..Default::default()
+        };
+        // Running from a subdirectory formats the same files as from the project root
+        for cwd in [dir.path().to_path_buf(), dir.path().join("entry/src")] {
+            let patterns = super::anchor_file_patterns(&cwd, &args);
+            assert_eq!(
+                patterns,
+                [
+                    format!("{}/entry/**/*.ets", dir.path().display()),
+                    format!("!{}/**/Gen.ets", dir.path().display()),
+                ]
+            );
+            let files = super::collect_matching_files(&patterns, &cwd, false, 1)
+                .expect("Files should be collected");
+            let root = dir
+                .path()
+                .canonicalize()
+                .expect("Failed to canonicalize temp dir");
+            assert_eq!(files, [root.join("entry/src/Index.ets")]);
+        }
+    }
+
+    #[test]
+    fn test_cli_flags_override_config_file() {
+        let dir = TempDir::new("config");
+        std::fs::write(
+            dir.path().join(".oxfmtrc.json"),
+            r#"{ "tabWidth": 4, "printWidth": 80 }"#,
+        )
+        .expect("Failed to write temp file");
+
+        let args = crate::FormatArgs {
+            line_width: Some("120".parse().expect("Valid line width")),
+            ..Default::default()
+        };
+        let (config_resolver, _) =
+            super::load_config(dir.path(), &args).expect("Config should load");
+        let strategy = FormatFileStrategy::try_from(dir.path().join("index.ets"))
+            .expect("ArkTS file should be recognized");
+
+        match super::resolve_options(&strategy, &config_resolver, "") {
+            Ok(ResolvedOptions::OxcFormatter { format_options, .. }) => {
+                assert_eq!(format_options.indent_width.value(), 4);
+                assert_eq!(format_options.line_width.value(), 120);
+            }
+            _ => panic!("ArkTS file should resolve OxcFormatter options"),
+        }
+    }
+
+    #[test]
+    fn test_trailing_commas_default_for_data_files() {
+        let dir = TempDir::new("trailing-commas");
+        let json5 = FormatFileStrategy::try_from(dir.path().join("module.json5"))
+            .expect("JSON5 file should be recognized");
+        let toml = FormatFileStrategy::try_from(dir.path().join("config.toml"))
+            .expect("TOML file should be recognized");
+        let trailing_commas = |args: crate::FormatArgs| {
+            let (config_resolver, _) =
+                super::load_config(dir.path(), &args).expect("Config should load");
+            let json = match super::resolve_options(&json5, &config_resolver, "") {
+                Ok(ResolvedOptions::OxfmtJson { json_options, .. }) => json_options.trailing_commas,
+                _ => panic!("JSON5 file should resolve OxfmtJson options"),
+            };
+            let toml = match super::resolve_options(&toml, &config_resolver, "") {
+                Ok(ResolvedOptions::OxfmtToml { toml_options, .. }) => {
+                    toml_options.array_trailing_comma
+                }
+                _ => panic!("TOML file should resolve OxfmtToml options"),
+            };
+            (json, toml)
+        };
+
+        assert_eq!(
+            trailing_commas(crate::FormatArgs::default()),
+            (false, false)
+        );
+        let args = crate::FormatArgs {
+            trailing_commas: Some("all".parse().expect("Valid trailing commas")),
+            ..Default::default()
+        };
+        assert_eq!(trailing_commas(args), (true, true));
+        let args = crate::FormatArgs {
+            trailing_commas: Some("none".parse().expect("Valid trailing commas")),
+            ..Default::default()
+        };
+        assert_eq!(trailing_commas(args), (false, false));
+
+        std::fs::write(
+            dir.path().join(".oxfmtrc.json"),
+            r#"{ "trailingComma": "es5" }"#,
+        )
+        .expect("Failed to write temp file");
+        assert_eq!(trailing_commas(crate::FormatArgs::default()), (true, true));
+    }
+
+    #[test]
+    fn test_editorconfig_below_config_file() {
+        let dir = TempDir::new("editorconfig");
+        std::fs::write(dir.path().join(".editorconfig"), "root = true\n\n[*]\nindent_style = tab\nmax_line_length = 100\n\n[*.ets]\nindent_size = 8\n").expect("Failed to write temp file");
+        std::fs::write(dir.path().join(".oxfmtrc.json"), r#"{ "printWidth": 80 }"#)
+            .expect("Failed to write temp file");
+
+        let (config_resolver, _) = super::load_config(dir.path(), &crate::FormatArgs::default())
+            .expect("Config should load");
+        let strategy = FormatFileStrategy::try_from(dir.path().join("index.ets"))
+            .expect("ArkTS file should be recognized");
+
+        match super::resolve_options(&strategy, &config_resolver, "") {
+            Ok(ResolvedOptions::OxcFormatter { format_options, .. }) => {
+                assert!(format_options.indent_style.is_tab());
+                assert_eq!(format_options.indent_width.value(), 8);
+                assert_eq!(format_options.line_width.value(), 80);
+            }
+            _ => panic!("ArkTS file should resolve OxcFormatter options"),
+        }
+    }
+
+    #[test]
+    fn test_editorconfig_in_subdirectories() {
+        let dir = TempDir::new("editorconfig-nested");
+        for sub in ["entry", "legacy"] {
+            std::fs::create_dir_all(dir.path().join(sub)).expect("Failed to create temp dir");
+        }
+        std::fs::write(
+            dir.path().join(".editorconfig"),
+            "[*]\nindent_size = 2\nmax_line_length = 120\n",
+        )
+        .expect("Failed to write temp file");
+        // A module overrides the project, and another one ignores it with `root = true`
+        std::fs::write(
+            dir.path().join("entry/.editorconfig"),
+            "[*.ets]\nindent_size = 4\n",
+        )
+        .expect("Failed to write temp file");
+        std::fs::write(
+            dir.path().join("legacy/.editorconfig"),
+            "root = true\n\n[*]\nindent_style = tab\n",
+        )
+        .expect("Failed to write temp file");
+
+        let (config_resolver, _) = super::load_config(dir.path(), &crate::FormatArgs::default())
+            .expect("Config should load");
+        let options = |path: &str| {
+            let strategy = FormatFileStrategy::try_from(dir.path().join(path))
+                .expect("ArkTS file should be recognized");
+            match super::resolve_options(&strategy, &config_resolver, "") {
+                Ok(ResolvedOptions::OxcFormatter { format_options, .. }) => format_options,
+                _ => panic!("ArkTS file should resolve OxcFormatter options"),
+            }
+        };
+
+        let root = options("Index.ets");
+        assert_eq!(root.indent_width.value(), 2);
+        assert_eq!(root.line_width.value(), 120);
+        let entry = options("entry/src/main/ets/pages/Index.ets");
+        assert_eq!(entry.indent_width.value(), 4);
+        assert_eq!(entry.line_width.value(), 120);
+        let legacy = options("legacy/Index.ets");
+        assert!(legacy.indent_style.is_tab());
+        assert_ne!(legacy.line_width.value(), 120);
+    }
+
     #[test]
     fn test_format_json5_file_strategy() {
         // Test that JSON5 files are recognized as OxfmtJson
@@ -680,6 +1974,7 @@ struct Index {
                 indent_width: 2,
                 use_tabs: false,
                 line_ending: "\n".to_string(),
+                line_width: 80,
                 trailing_commas: false,
                 quote_properties: json5format::QuoteProperties::Consistent,
             },
@@ -727,6 +2022,7 @@ struct Index {
                 indent_width: 2,
                 use_tabs: false,
                 line_ending: "\n".to_string(),
+                line_width: 80,
                 trailing_commas: false,
                 quote_properties: json5format::QuoteProperties::Consistent,
             },
@@ -755,7 +2051,7 @@ struct Index {
   "name": "test",
   "version": "1.0.0",
   /* Another comment */
-  "description": "Test package"
+  "description": "Test package",
 }"#;
 
         let path = PathBuf::from("test.jsonc");
@@ -778,6 +2074,7 @@ struct Index {
                 indent_width: 2,
                 use_tabs: false,
                 line_ending: "\n".to_string(),
+                line_width: 80,
                 trailing_commas: false,
                 quote_properties: json5format::QuoteProperties::Consistent,
             },
@@ -787,17 +2084,18 @@ struct Index {
 
         match formatter.format(&strategy, jsonc_content, resolved_options) {
             format::FormatResult::Success { code, .. } => {
-                assert!(!code.is_empty(), "Formatted JSONC should not be empty");
-                // Comments should be stripped
-                assert!(
-                    !code.contains("//"),
-                    "Comments should be stripped from JSONC"
-                );
-                assert!(
-                    !code.contains("/*"),
-                    "Comments should be stripped from JSONC"
+                // Comments are kept, the trailing comma is removed to keep strict JSON
+                assert_eq!(
+                    code,
+                    r#"{
+  // This is a comment
+  "name": "test",
+  "version": "1.0.0",
+  /* Another comment */
+  "description": "Test package"
+}
+"#
                 );
-                assert!(code.contains("name"), "Should contain 'name'");
             }
             format::FormatResult::Error(diagnostics) => {
                 panic!(
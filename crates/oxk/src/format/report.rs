@@ -0,0 +1,597 @@
+use std::{fmt::Write, path::PathBuf, str::FromStr};
+
+use oxc_diagnostics::{Error, GraphicalReportHandler, GraphicalTheme, NamedSource, OxcDiagnostic};
+use serde_json::{Value, json};
+
+use super::{FileStatus, display_path};
+
+const TOOL_NAME: &str = "oxk";
+const TOOL_URI: &str = "https://github.com/ohos-rs/oxc-ark";
+
+/// Output format of the formatting results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Reporter {
+    /// Human readable messages on stderr.
+    #[default]
+    Default,
+    Json,
+    Sarif,
+    Checkstyle,
+    Junit,
+    /// GitHub Actions workflow commands, shown as annotations.
+    Github,
+}
+
+impl FromStr for Reporter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Self::Default),
+            "json" => Ok(Self::Json),
+            "sarif" => Ok(Self::Sarif),
+            "checkstyle" => Ok(Self::Checkstyle),
+            "junit" => Ok(Self::Junit),
+            "github" => Ok(Self::Github),
+            _ => Err(format!(
+                "Invalid reporter '{s}'. Values: default, json, sarif, checkstyle, junit, github"
+            )),
+        }
+    }
+}
+
+/// Result of formatting a single file.
+#[derive(Debug)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub status: FileStatus,
+    /// Source the diagnostic spans point into, if the file was read.
+    pub source_text: Option<String>,
+    /// Errors for `FileStatus::Failed`.
+    pub diagnostics: Vec<OxcDiagnostic>,
+}
+
+impl FileReport {
+    pub fn new(path: PathBuf, status: FileStatus) -> Self {
+        Self {
+            path,
+            status,
+            source_text: None,
+            diagnostics: Vec::new(),
+        }
+    }
+}
+
+/// Number of files per outcome, printed after all files are processed.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub changed: usize,
+    pub unchanged: usize,
+    pub skipped: usize,
+    pub unsupported: usize,
+    pub failed: usize,
+}
+
+impl Summary {
+    pub fn from_reports(reports: &[FileReport]) -> Self {
+        let mut summary = Self::default();
+        for report in reports {
+            match report.status {
+                FileStatus::Changed => summary.changed += 1,
+                FileStatus::Unchanged => summary.unchanged += 1,
+                FileStatus::Skipped => summary.skipped += 1,
+                FileStatus::Unsupported => summary.unsupported += 1,
+                FileStatus::Failed => summary.failed += 1,
+            }
+        }
+        summary
+    }
+
+    /// Failures take precedence over unformatted files in `--check` and `--diff` mode.
+    pub fn exit_code(&self, check: bool) -> i32 {
+        if self.failed > 0 {
+            super::EXIT_CODE_FAILED
+        } else if check && self.changed > 0 {
+            super::EXIT_CODE_UNFORMATTED
+        } else {
+            0
+        }
+    }
+
+    pub fn render(&self, check: bool) -> String {
+        let total = self.changed + self.unchanged + self.skipped + self.unsupported + self.failed;
+        let (verb, changed) = if check {
+            ("Checked", "unformatted")
+        } else {
+            ("Processed", "formatted")
+        };
+        format!(
+            "{verb} {total} file(s): {} {changed}, {} unchanged, {} skipped, {} unsupported, {} failed",
+            self.changed, self.unchanged, self.skipped, self.unsupported, self.failed
+        )
+    }
+}
+
+/// Render diagnostics as code frames with the file path, position, labels and help, like oxc's own tools.
+pub fn render_code_frames(report: &FileReport, color: bool) -> String {
+    let theme = if color {
+        GraphicalTheme::unicode()
+    } else {
+        GraphicalTheme::unicode_nocolor()
+    };
+    let handler = GraphicalReportHandler::new_themed(theme).with_links(false);
+    let path = display_path(&report.path);
+
+    let mut output = String::new();
+    for diagnostic in &report.diagnostics {
+        let error: Error = match &report.source_text {
+            Some(source_text) => diagnostic
+                .clone()
+                .with_source_code(NamedSource::new(&path, source_text.clone())),
+            None => Error::new(diagnostic.clone()),
+        };
+        let _ = handler.render_report(&mut output, error.as_ref());
+    }
+    output
+}
+
+/// A message about a file, with a 1-based position if it points into the source.
+struct Message {
+    rule: &'static str,
+    level: Level,
+    text: String,
+    help: Option<String>,
+    start: Option<(usize, usize)>,
+    end: Option<(usize, usize)>,
+}
+
+#[derive(Clone, Copy)]
+enum Level {
+    Error,
+    Warning,
+    Note,
+}
+
+/// Render the results of all files, sorted by path.
+///
+/// In check mode, changed files are reported as errors instead of notes.
+pub fn render(reporter: Reporter, reports: &[FileReport], check: bool) -> String {
+    let mut reports: Vec<_> = reports.iter().collect();
+    reports.sort_by(|a, b| a.path.cmp(&b.path));
+    let files: Vec<_> = reports
+        .iter()
+        .map(|report| (display_path(&report.path), *report, messages(report, check)))
+        .collect();
+
+    match reporter {
+        Reporter::Default => String::new(),
+        Reporter::Json => render_json(&files),
+        Reporter::Sarif => render_sarif(&files),
+        Reporter::Checkstyle => render_checkstyle(&files),
+        Reporter::Junit => render_junit(&files),
+        Reporter::Github => render_github(&files),
+    }
+}
+
+type RenderedFile<'a> = (String, &'a FileReport, Vec<Message>);
+
+fn messages(report: &FileReport, check: bool) -> Vec<Message> {
+    let file_message = |rule, level, text: &str| Message {
+        rule,
+        level,
+        text: text.to_string(),
+        help: None,
+        start: None,
+        end: None,
+    };
+
+    match report.status {
+        FileStatus::Changed if check => vec![file_message(
+            "format",
+            Level::Error,
+            "File is not formatted",
+        )],
+        FileStatus::Changed => vec![file_message("format", Level::Note, "File was formatted")],
+        FileStatus::Unsupported => vec![file_message(
+            "unsupported",
+            Level::Warning,
+            "Unsupported file type",
+        )],
+        FileStatus::Failed => report
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let span = diagnostic
+                    .labels
+                    .as_ref()
+                    .and_then(|labels| labels.first())
+                    .zip(report.source_text.as_deref());
+                Message {
+                    // Errors without source are I/O errors
+                    rule: if report.source_text.is_some() {
+                        "parse"
+                    } else {
+                        "error"
+                    },
+                    level: Level::Error,
+                    text: diagnostic.message.to_string(),
+                    help: diagnostic.help.as_ref().map(ToString::to_string),
+                    start: span.map(|(label, source)| line_column(source, label.offset())),
+                    end: span
+                        .map(|(label, source)| line_column(source, label.offset() + label.len())),
+                }
+            })
+            .collect(),
+        FileStatus::Unchanged | FileStatus::Skipped => Vec::new(),
+    }
+}
+
+/// Convert a byte offset into a 1-based line and column, counting columns in characters.
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(source.len());
+    let before = source.get(..offset).unwrap_or(source);
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line = before.matches('\n').count() + 1;
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+fn status_name(status: FileStatus) -> &'static str {
+    match status {
+        FileStatus::Changed => "changed",
+        FileStatus::Unchanged => "unchanged",
+        FileStatus::Skipped => "skipped",
+        FileStatus::Unsupported => "unsupported",
+        FileStatus::Failed => "failed",
+    }
+}
+
+fn render_json(files: &[RenderedFile]) -> String {
+    let position = |position: Option<(usize, usize)>| {
+        position.map_or(
+            Value::Null,
+            |(line, column)| json!({ "line": line, "column": column }),
+        )
+    };
+    let files: Vec<Value> = files
+        .iter()
+        .map(|(path, report, messages)| {
+            let diagnostics: Vec<Value> = messages
+                .iter()
+                .filter(|_| report.status == FileStatus::Failed)
+                .map(|message| {
+                    json!({
+                        "message": message.text,
+                        "help": message.help,
+                        "start": position(message.start),
+                        "end": position(message.end),
+                    })
+                })
+                .collect();
+            json!({
+                "path": path,
+                "status": status_name(report.status),
+                "diagnostics": diagnostics,
+            })
+        })
+        .collect();
+
+    format!("{}\n", json!({ "files": files }))
+}
+
+fn render_sarif(files: &[RenderedFile]) -> String {
+    let results: Vec<Value> = files
+        .iter()
+        .flat_map(|(path, _, messages)| {
+            messages.iter().map(move |message| {
+                let mut location = json!({ "artifactLocation": { "uri": path } });
+                if let (Some((start_line, start_column)), Some((end_line, end_column))) =
+                    (message.start, message.end)
+                {
+                    location["region"] = json!({
+                        "startLine": start_line,
+                        "startColumn": start_column,
+                        "endLine": end_line,
+                        "endColumn": end_column,
+                    });
+                }
+                let text = match &message.help {
+                    Some(help) => format!("{}\nhelp: {help}", message.text),
+                    None => message.text.clone(),
+                };
+                json!({
+                    "ruleId": message.rule,
+                    "level": match message.level {
+                        Level::Error => "error",
+                        Level::Warning => "warning",
+                        Level::Note => "note",
+                    },
+                    "message": { "text": text },
+                    "locations": [{ "physicalLocation": location }],
+                })
+            })
+        })
+        .collect();
+
+    let sarif = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": TOOL_NAME,
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": TOOL_URI,
+                }
+            },
+            // Columns are counted in characters rather than the default UTF-16 code units
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        }],
+    });
+    format!("{}\n", sarif)
+}
+
+fn render_checkstyle(files: &[RenderedFile]) -> String {
+    let mut output = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    output.push_str("<checkstyle version=\"4.3\">\n");
+    for (path, _, messages) in files {
+        if messages.is_empty() {
+            continue;
+        }
+        let _ = writeln!(output, "  <file name=\"{}\">", escape_xml(path));
+        for message in messages {
+            let (line, column) = message.start.unwrap_or((1, 1));
+            let severity = match message.level {
+                Level::Error => "error",
+                Level::Warning => "warning",
+                Level::Note => "info",
+            };
+            let _ = writeln!(
+                output,
+                "    <error line=\"{line}\" column=\"{column}\" severity=\"{severity}\" message=\"{}\" source=\"{TOOL_NAME}.{}\"/>",
+                escape_xml(&message.text),
+                message.rule
+            );
+        }
+        output.push_str("  </file>\n");
+    }
+    output.push_str("</checkstyle>\n");
+    output
+}
+
+fn render_junit(files: &[RenderedFile]) -> String {
+    let failures = files
+        .iter()
+        .filter(|(_, _, messages)| messages.iter().any(|m| matches!(m.level, Level::Error)))
+        .count();
+
+    let mut output = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    let _ = writeln!(
+        output,
+        "<testsuites name=\"{TOOL_NAME}\" tests=\"{}\" failures=\"{failures}\">",
+        files.len()
+    );
+    let _ = writeln!(
+        output,
+        "  <testsuite name=\"{TOOL_NAME} format\" tests=\"{}\" failures=\"{failures}\">",
+        files.len()
+    );
+    for (path, report, messages) in files {
+        let path = escape_xml(path);
+        let _ = write!(
+            output,
+            "    <testcase name=\"{path}\" classname=\"{TOOL_NAME}.format\""
+        );
+        let errors: Vec<_> = messages
+            .iter()
+            .filter(|m| matches!(m.level, Level::Error))
+            .collect();
+
+        if let Some(first) = errors.first() {
+            output.push_str(">\n");
+            let details: Vec<_> = errors
+                .iter()
+                .map(|m| match m.start {
+                    Some((line, column)) => format!("{path}:{line}:{column}: {}", m.text),
+                    None => format!("{path}: {}", m.text),
+                })
+                .collect();
+            let _ = writeln!(
+                output,
+                "      <failure message=\"{}\" type=\"{}\">{}</failure>",
+                escape_xml(&first.text),
+                first.rule,
+                escape_xml(&details.join("\n"))
+            );
+            output.push_str("    </testcase>\n");
+        } else if matches!(report.status, FileStatus::Skipped | FileStatus::Unsupported) {
+            output.push_str(">\n      <skipped/>\n    </testcase>\n");
+        } else {
+            output.push_str("/>\n");
+        }
+    }
+    output.push_str("  </testsuite>\n</testsuites>\n");
+    output
+}
+
+fn render_github(files: &[RenderedFile]) -> String {
+    let mut output = String::new();
+    for (path, _, messages) in files {
+        for message in messages {
+            let command = match message.level {
+                Level::Error => "error",
+                Level::Warning => "warning",
+                Level::Note => "notice",
+            };
+            let mut properties = format!("file={}", escape_github_property(path));
+            if let (Some((line, column)), Some((end_line, end_column))) =
+                (message.start, message.end)
+            {
+                let _ = write!(
+                    properties,
+                    ",line={line},col={column},endLine={end_line},endColumn={end_column}"
+                );
+            }
+            let text = match &message.help {
+                Some(help) => format!("{}\nhelp: {help}", message.text),
+                None => message.text.clone(),
+            };
+            let _ = writeln!(
+                output,
+                "::{command} {properties},title={TOOL_NAME}::{}",
+                escape_github_data(&text)
+            );
+        }
+    }
+    output
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn escape_github_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_github_property(text: &str) -> String {
+    escape_github_data(text)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use oxc_diagnostics::OxcDiagnostic;
+    use oxc_span::Span;
+
+    use super::{FileReport, Reporter, Summary, line_column, render, render_code_frames};
+    use crate::format::FileStatus;
+
+    fn reports() -> Vec<FileReport> {
+        let mut failed = FileReport::new(PathBuf::from("pages/Broken.ets"), FileStatus::Failed);
+        failed.source_text = Some("let a = 1\nlet b = (\n".to_string());
+        failed.diagnostics = vec![
+            OxcDiagnostic::error("Unexpected token")
+                .with_label(Span::new(18, 19))
+                .with_help("Try closing the parenthesis"),
+        ];
+        vec![
+            FileReport::new(PathBuf::from("pages/Index.ets"), FileStatus::Changed),
+            FileReport::new(PathBuf::from("pages/Other.ets"), FileStatus::Unchanged),
+            FileReport::new(PathBuf::from("README.md"), FileStatus::Unsupported),
+            failed,
+        ]
+    }
+
+    #[test]
+    fn test_line_column() {
+        let source = "let a = 1\nlet 名 = 2\n";
+        assert_eq!(line_column(source, 0), (1, 1));
+        assert_eq!(line_column(source, 10), (2, 1));
+        assert_eq!(line_column(source, 17), (2, 6));
+    }
+
+    #[test]
+    fn test_summary() {
+        let summary = Summary::from_reports(&reports());
+        assert_eq!(
+            summary.render(true),
+            "Checked 4 file(s): 1 unformatted, 1 unchanged, 0 skipped, 1 unsupported, 1 failed"
+        );
+        assert_eq!(summary.exit_code(true), crate::format::EXIT_CODE_FAILED);
+
+        let summary = Summary::from_reports(&reports()[..3]);
+        assert_eq!(
+            summary.exit_code(true),
+            crate::format::EXIT_CODE_UNFORMATTED
+        );
+        assert_eq!(summary.exit_code(false), 0);
+    }
+
+    #[test]
+    fn test_render_code_frames() {
+        let reports = reports();
+        let output = render_code_frames(&reports[3], false);
+
+        assert!(output.contains("Unexpected token"));
+        assert!(output.contains("pages/Broken.ets:2:9"));
+        assert!(output.contains("2 │ let b = ("));
+        assert!(output.contains("help: Try closing the parenthesis"));
+    }
+
+    #[test]
+    fn test_json_reporter() {
+        let output = render(Reporter::Json, &reports(), true);
+        let value: serde_json::Value = serde_json::from_str(&output).expect("Valid JSON");
+        let files = value["files"].as_array().expect("Files array");
+
+        assert_eq!(files[0]["path"], "README.md");
+        assert_eq!(files[0]["status"], "unsupported");
+        assert_eq!(files[1]["status"], "failed");
+        let diagnostic = &files[1]["diagnostics"][0];
+        assert_eq!(diagnostic["message"], "Unexpected token");
+        assert_eq!(diagnostic["help"], "Try closing the parenthesis");
+        assert_eq!(diagnostic["start"]["line"], 2);
+        assert_eq!(diagnostic["start"]["column"], 9);
+        assert_eq!(files[2]["status"], "changed");
+    }
+
+    #[test]
+    fn test_sarif_reporter() {
+        let output = render(Reporter::Sarif, &reports(), true);
+        let value: serde_json::Value = serde_json::from_str(&output).expect("Valid JSON");
+        let run = &value["runs"][0];
+
+        assert_eq!(run["columnKind"], "unicodeCodePoints");
+        let region = &run["results"][1]["locations"][0]["physicalLocation"]["region"];
+        assert_eq!(region["startLine"], 2);
+        assert_eq!(region["startColumn"], 9);
+    }
+
+    #[test]
+    fn test_github_reporter() {
+        let output = render(Reporter::Github, &reports(), true);
+        assert_eq!(
+            output,
+            "::warning file=README.md,title=oxk::Unsupported file type\n\
+             ::error file=pages/Broken.ets,line=2,col=9,endLine=2,endColumn=10,title=oxk::Unexpected token%0Ahelp: Try closing the parenthesis\n\
+             ::error file=pages/Index.ets,title=oxk::File is not formatted\n"
+        );
+    }
+
+    #[test]
+    fn test_checkstyle_and_junit_reporters() {
+        let checkstyle = render(Reporter::Checkstyle, &reports(), true);
+        assert!(checkstyle.contains(
+            "<error line=\"2\" column=\"9\" severity=\"error\" message=\"Unexpected token\" source=\"oxk.parse\"/>"
+        ));
+        assert!(!checkstyle.contains("Other.ets"));
+
+        let junit = render(Reporter::Junit, &reports(), true);
+        assert!(junit.contains("<testsuites name=\"oxk\" tests=\"4\" failures=\"2\">"));
+        assert!(junit.contains("<testcase name=\"pages/Other.ets\" classname=\"oxk.format\"/>"));
+        assert!(
+            junit.contains(
+                "<testcase name=\"README.md\" classname=\"oxk.format\">\n      <skipped/>"
+            )
+        );
+    }
+}
@@ -0,0 +1,291 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{DefaultHasher, Hash, Hasher},
+    io::IsTerminal,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        mpsc::{Receiver, RecvTimeoutError},
+    },
+    time::Duration,
+};
+
+use format::{resolve_editorconfig_path, resolve_oxfmtrc_path};
+use globset::GlobSet;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use owo_colors::OwoColorize;
+
+use super::{FileStatus, FormatContext, IGNORE_FILENAMES};
+
+/// Quiet period after the last change before a batch of files is formatted.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Config files that are reloaded when they change.
+const CONFIG_FILENAMES: [&str; 3] = [".oxfmtrc.json", ".oxfmtrc.jsonc", ".editorconfig"];
+
+/// Watch the roots of the file patterns and format matched files when they change.
+///
+/// Runs until the watcher stops, e.g. when the process is interrupted.
+pub fn watch(cwd: &Path, args: &crate::FormatArgs) -> Result<i32, Box<dyn std::error::Error>> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)
+        .map_err(|e| format!("Failed to start file watcher: {}", e))?;
+
+    let mut roots = HashSet::new();
+    for pattern in args
+        .file
+        .iter()
+        .filter(|pattern| !super::is_negated(pattern))
+    {
+        let root = super::determine_root(&super::to_absolute_pattern(pattern, cwd), cwd);
+        // Editors often replace files on save, so watch the directory instead of the file
+        let root = match root.parent() {
+            Some(parent) if root.is_file() => parent.to_path_buf(),
+            _ => root,
+        };
+        roots.insert(root);
+    }
+    // Config files may live above the watched roots, or be created in the current directory
+    let config_paths = [
+        resolve_oxfmtrc_path(cwd, args.config.as_deref()),
+        resolve_editorconfig_path(cwd),
+        Some(cwd.to_path_buf()),
+    ];
+    let config_paths: HashSet<_> = config_paths
+        .into_iter()
+        .flatten()
+        .filter(|path| !roots.iter().any(|root| path.starts_with(root)))
+        .collect();
+
+    for (paths, mode) in [
+        (&roots, RecursiveMode::Recursive),
+        (&config_paths, RecursiveMode::NonRecursive),
+    ] {
+        for path in paths {
+            watcher
+                .watch(path, mode)
+                .map_err(|e| format!("Failed to watch '{}': {}", path.display(), e))?;
+        }
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| format!("Failed to create tokio runtime: {}", e))?;
+
+    // Event paths are absolute, while `--config` may be relative, e.g. `./.oxfmtrc.json`
+    let explicit_config = args
+        .config
+        .as_deref()
+        .map(|config| super::normalize_path(&cwd.join(config)))
+        .transpose()?;
+
+    let (mut context, mut exclude_matcher) = load_watch_config(cwd, args)?;
+    // Hashes of the contents written by the watcher, so its own writes do not trigger again
+    let mut written = HashMap::new();
+
+    let color = super::use_color(args, std::io::stderr().is_terminal());
+
+    eprintln!("Watching for changes...");
+
+    while let Some(changed) = next_batch(&rx) {
+        if changed
+            .iter()
+            .any(|path| is_config_file(path, explicit_config.as_deref()))
+        {
+            match load_watch_config(cwd, args) {
+                Ok((new_context, matcher)) => {
+                    context = new_context;
+                    exclude_matcher = matcher;
+                    eprintln!("Configuration reloaded");
+                }
+                Err(e) => eprintln!("{}", paint_error(&e.to_string(), color)),
+            }
+        }
+        if changed.iter().any(|path| is_ignore_file(path)) {
+            eprintln!("Ignore files reloaded");
+        }
+
+        // Walk again so that changed ignore files and new files are taken into account
+        let files: HashSet<PathBuf> =
+            match super::collect_matching_files(&args.file, cwd, args.follow_symlinks, args.thread)
+            {
+                Ok(files) => files
+                    .into_iter()
+                    .filter(|path| {
+                        exclude_matcher.as_ref().is_none_or(|matcher| {
+                            !matcher.is_match(path.to_string_lossy().as_ref())
+                        })
+                    })
+                    .collect(),
+                Err(e) => {
+                    eprintln!("{}", paint_error(&e.to_string(), color));
+                    continue;
+                }
+            };
+
+        for path in changed {
+            let Ok(path) = super::normalize_path(&path) else {
+                continue;
+            };
+            if !files.contains(&path) {
+                continue;
+            }
+            let Ok(bytes) = std::fs::read(&path) else {
+                continue;
+            };
+            if written.get(&path) == Some(&content_hash(&bytes)) {
+                continue;
+            }
+
+            let result = runtime.block_on(super::format_file_async(&path, &context));
+            match result {
+                Ok(FileStatus::Changed) => {
+                    eprintln!("Formatted {}", super::display_path(&path));
+                    if let Ok(bytes) = std::fs::read(&path) {
+                        written.insert(path, content_hash(&bytes));
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => super::print_file_report(&e.into_report(path), color),
+            }
+        }
+    }
+
+    Ok(0)
+}
+
+/// Load the config and the exclude matcher built from `--exclude` and `ignorePatterns`.
+fn load_watch_config(
+    cwd: &Path,
+    args: &crate::FormatArgs,
+) -> Result<(Arc<FormatContext>, Option<GlobSet>), Box<dyn std::error::Error>> {
+    let (config_resolver, excludes) = super::load_config(cwd, args)?;
+    let context = Arc::new(FormatContext::new(config_resolver, None, args.clone()));
+    Ok((context, super::build_globset(&excludes, cwd)?))
+}
+
+/// Wait for the next change and collect the paths changed until no event arrives for `DEBOUNCE`.
+///
+/// Returns `None` when the watcher is dropped.
+fn next_batch(rx: &Receiver<notify::Result<Event>>) -> Option<HashSet<PathBuf>> {
+    let mut changed = HashSet::new();
+    let mut event = rx.recv().ok()?;
+
+    loop {
+        match event {
+            Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                changed.extend(event.paths);
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Warning: {}", e),
+        }
+
+        event = match rx.recv_timeout(DEBOUNCE) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) if changed.is_empty() => rx.recv().ok()?,
+            Err(RecvTimeoutError::Timeout) => return Some(changed),
+            Err(RecvTimeoutError::Disconnected) => return None,
+        };
+    }
+}
+
+/// Errors are red, unless color is disabled like for code frames.
+fn paint_error(message: &str, color: bool) -> String {
+    if color {
+        message.red().to_string()
+    } else {
+        message.to_string()
+    }
+}
+
+fn is_config_file(path: &Path, explicit_config: Option<&Path>) -> bool {
+    let is_explicit_config = explicit_config.is_some_and(|config| {
+        path == config || super::normalize_path(path).is_ok_and(|path| path == config)
+    });
+    is_explicit_config
+        || path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| CONFIG_FILENAMES.contains(&name))
+}
+
+fn is_ignore_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name == ".gitignore" || IGNORE_FILENAMES.contains(&name))
+}
+
+fn content_hash(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::mpsc;
+
+    use notify::{
+        Event, EventKind,
+        event::{CreateKind, ModifyKind, RemoveKind},
+    };
+
+    use super::{is_config_file, next_batch, paint_error};
+    use crate::format::{normalize_path, test_utils::TempDir};
+
+    #[test]
+    fn test_paint_error() {
+        assert_eq!(paint_error("Failed", false), "Failed");
+        assert_ne!(paint_error("Failed", true), "Failed");
+    }
+
+    #[test]
+    fn test_next_batch_debounces_changes() {
+        let (tx, rx) = mpsc::channel();
+        let path = PathBuf::from("/project/entry/src/main/ets/pages/Index.ets");
+        tx.send(Ok(
+            Event::new(EventKind::Create(CreateKind::File)).add_path(path.clone())
+        ))
+        .expect("Failed to send event");
+        tx.send(Ok(
+            Event::new(EventKind::Modify(ModifyKind::Any)).add_path(path.clone())
+        ))
+        .expect("Failed to send event");
+        tx.send(Ok(
+            Event::new(EventKind::Remove(RemoveKind::File)).add_path(PathBuf::from("/old.ets"))
+        ))
+        .expect("Failed to send event");
+
+        let batch = next_batch(&rx).expect("Batch should be collected");
+        assert_eq!(batch.into_iter().collect::<Vec<_>>(), [path]);
+
+        drop(tx);
+        assert!(next_batch(&rx).is_none());
+    }
+
+    #[test]
+    fn test_is_config_file() {
+        let dir = TempDir::new("watch-config");
+        for sub in ["config", "entry"] {
+            std::fs::create_dir_all(dir.path().join(sub)).expect("Failed to create temp dir");
+        }
+        let config = dir.path().join("config/format.json");
+        std::fs::write(&config, "{}").expect("Failed to write temp file");
+        std::fs::write(dir.path().join("entry/Index.ets"), "").expect("Failed to write temp file");
+        // `--config ../config/format.json` from the `entry` directory
+        let explicit_config = normalize_path(&dir.path().join("entry/../config/format.json"))
+            .expect("Config path should be normalized");
+
+        assert!(is_config_file(&config, Some(&explicit_config)));
+        assert!(!is_config_file(
+            &dir.path().join("config/other.json"),
+            Some(&explicit_config)
+        ));
+        assert!(is_config_file(
+            &dir.path().join("entry/.editorconfig"),
+            None
+        ));
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+};
+
+/// Replace the content of `path` through a temporary file in the same directory,
+/// so an interrupted run or a crash never leaves the file truncated.
+///
+/// The temporary file is flushed to disk before the rename, and on Unix the rename itself is
+/// flushed through the directory. The permissions of an existing file are kept.
+pub fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::other(format!("'{}' is not a file", path.display())))?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(".oxk-{}.tmp", std::process::id()));
+    let temp_path = path.with_file_name(temp_name);
+
+    let permissions = std::fs::metadata(path).ok().map(|m| m.permissions());
+    let result = write_synced(&temp_path, content)
+        .and_then(|()| match permissions {
+            Some(permissions) => std::fs::set_permissions(&temp_path, permissions),
+            None => Ok(()),
+        })
+        .and_then(|()| std::fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result?;
+    sync_parent_dir(path)
+}
+
+fn write_synced(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+/// Persist a rename into the directory of `path`, directories cannot be opened on Windows.
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => File::open(parent)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Whether `path` lies inside the canonical `root` lexically, but its `target` does not,
+/// i.e. it is reached through a symlink that points outside of `root`.
+pub fn escapes_root(path: &Path, target: &Path, root: &Path) -> bool {
+    normalize_lexically(path).starts_with(root) && !target.starts_with(root)
+}
+
+/// Resolve `.` and `..` components without touching the file system.
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{escapes_root, write_atomic};
+    use crate::format::test_utils::TempDir;
+
+    #[test]
+    fn test_write_atomic_keeps_permissions() {
+        let dir = TempDir::new("write");
+        let path = dir.path().join("hvigorw");
+        std::fs::write(&path, "old").expect("Failed to write temp file");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+                .expect("Failed to set permissions");
+        }
+
+        write_atomic(&path, b"new").expect("Write should succeed");
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("hvigorw")).expect("Failed to read temp file"),
+            "new"
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path)
+                .expect("Metadata")
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+        // No temporary file is left behind
+        assert_eq!(
+            std::fs::read_dir(dir.path())
+                .expect("Failed to read dir")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_escapes_root() {
+        let root = Path::new("/project");
+        assert!(escapes_root(
+            Path::new("/project/entry/Index.ets"),
+            Path::new("/shared/Index.ets"),
+            root
+        ));
+        assert!(!escapes_root(
+            Path::new("/project/entry/Index.ets"),
+            Path::new("/project/common/Index.ets"),
+            root
+        ));
+        // Files outside of the root are only formatted when requested explicitly
+        assert!(!escapes_root(
+            Path::new("/project/../shared/Index.ets"),
+            Path::new("/shared/Index.ets"),
+            root
+        ));
+    }
+}
@@ -5,15 +5,33 @@ use crate::cli::cli_run;
 mod cli;
 mod format;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub(crate) struct FormatArgs {
     file: Vec<String>,
     thread: usize,
     excludes: Vec<String>,
+    check: bool,
+    diff: bool,
+    stdin_filepath: Option<std::path::PathBuf>,
+    config: Option<std::path::PathBuf>,
+    watch: bool,
+    cache: bool,
+    cache_location: Option<std::path::PathBuf>,
+    reporter: format::Reporter,
+    staged: bool,
+    changed: bool,
+    since: Option<String>,
+    color: Option<bool>,
+    fail_fast: bool,
+    encoding: Option<&'static encoding_rs::Encoding>,
+    out_dir: Option<std::path::PathBuf>,
+    files_from: Option<std::path::PathBuf>,
+    null_separated: bool,
+    follow_symlinks: bool,
     // FormatOptions fields (excluding quote_properties)
     pub indent_style: Option<oxc_formatter::IndentStyle>,
     pub indent_width: Option<oxc_formatter::IndentWidth>,
-    pub line_ending: Option<oxc_formatter::LineEnding>,
+    pub line_ending: Option<::format::EndOfLine>,
     pub line_width: Option<oxc_formatter::LineWidth>,
     pub quote_style: Option<oxc_formatter::QuoteStyle>,
     pub jsx_quote_style: Option<oxc_formatter::QuoteStyle>,
@@ -46,8 +64,13 @@ fn main() {
     let run_ret = match ret {
         Options::Format(args) => format::format(args),
     };
-    if let Err(e) = run_ret {
-        println!("{:?}", e.red());
-        std::process::exit(-1);
+    match run_ret {
+        Ok(0) => {}
+        Ok(code) => std::process::exit(code),
+        Err(e) => {
+            // Keep stdout clean for formatted output (e.g. `--stdin-filepath`)
+            eprintln!("{}", e.to_string().red());
+            std::process::exit(-1);
+        }
     }
 }
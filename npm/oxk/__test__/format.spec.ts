@@ -151,3 +151,21 @@ test('format regular TypeScript file', async (t) => {
     t.fail('TypeScript formatting should not have errors')
   }
 })
+
+test('keep the line ending of the source with endOfLine auto', async (t) => {
+  const source = 'const x=1;\r\nconst y=2;\r\n'
+
+  const result = await format('test.ts', source, { endOfLine: 'auto' })
+
+  t.is(result.errors.length, 0, 'Should not have errors')
+  t.is(result.code, 'const x = 1;\r\nconst y = 2;\r\n')
+})
+
+test('keep JSON5 files without trailing commas by default', async (t) => {
+  const source = '{\n  "name": "test",\n  "version": "1.0.0"\n}\n'
+
+  const result = await format('config.json5', source, undefined)
+
+  t.is(result.errors.length, 0, 'Should not have errors')
+  t.is(result.code, source)
+})
@@ -128,7 +128,8 @@ pub async fn format(
     }
   }
 
-  let mut resolved_options = config_resolver.resolve(&strategy);
+  // `endOfLine: "auto"` keeps the most common line ending of the source
+  let mut resolved_options = config_resolver.resolve_with_source(&strategy, &source_text);
 
   // Fix quote_properties: Oxfmtrc's deserialization may not properly handle quoteProperties,
   // so we manually override it to Always for JSON/JSON5/JSONC files